@@ -0,0 +1,68 @@
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use crate::types::{SpendAuthorization, SpendDomain};
+
+const SPEND_DOMAIN: &[u8] = b"IntelliFi spend authorization";
+
+// Message the note owner signs to authorize spending one note, named by its
+// nullifier, to `recipient` on one deployment
+pub fn spend_message_hash(
+    wallet_address: &[u8; 20],
+    nullifier: &[u8; 32],
+    merkle_root: &[u8; 32],
+    amount: u64,
+    recipient: &[u8; 20],
+    relayer_fee: u64,
+    domain: &SpendDomain,
+) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(SPEND_DOMAIN);
+    hasher.update(domain.chain_id.to_be_bytes());
+    hasher.update(domain.verifier_address);
+    hasher.update(wallet_address);
+    hasher.update(nullifier);
+    hasher.update(merkle_root);
+    hasher.update(amount.to_be_bytes());
+    hasher.update(recipient);
+    hasher.update(relayer_fee.to_be_bytes());
+    hasher.finalize().into()
+}
+
+// EIP-191 personal_sign digest, so browser wallets can produce the signature
+pub fn eth_signed_message_hash(message_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n32");
+    hasher.update(message_hash);
+    hasher.finalize().into()
+}
+
+// Recover the Ethereum address behind a 65-byte r || s || v signature
+pub fn recover_address(digest: &[u8; 32], signature: &[u8]) -> Option<[u8; 20]> {
+    if signature.len() != 65 {
+        return None;
+    }
+    let parsed = Signature::from_slice(&signature[..64]).ok()?;
+    let v = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_byte(v)?;
+    let key = VerifyingKey::recover_from_prehash(digest, &parsed, recovery_id).ok()?;
+
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Some(address)
+}
+
+// Verify the note owner signed this spend
+pub fn verify_authorization(
+    wallet_address: &[u8; 20],
+    authorization: &SpendAuthorization,
+    message_hash: &[u8; 32],
+) -> bool {
+    recover_address(&eth_signed_message_hash(message_hash), &authorization.signature)
+        == Some(*wallet_address)
+}
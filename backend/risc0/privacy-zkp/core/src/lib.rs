@@ -0,0 +1,358 @@
+// Spend types and the hashing around them, owned in one place.
+//
+// The guest proves with these functions, and the host, drew-v and the napi
+// addon build their inputs with them, so a leaf, nullifier or commitment is
+// computed the same way everywhere. Inputs travel between processes in the
+// canonical encoding (postcard) of these types.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+pub mod auth;
+pub mod pedersen;
+pub mod types;
+pub mod verify;
+
+pub use privacy_zkp_merkle as merkle;
+pub use privacy_zkp_merkle::MerkleTree;
+pub use types::*;
+pub use verify::{verify_batch, verify_spend};
+
+pub type CodecError = postcard::Error;
+
+// Canonical byte encoding of a spend type
+pub fn to_canonical_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError> {
+    postcard::to_allocvec(value)
+}
+
+pub fn from_canonical_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+    postcard::from_bytes(bytes)
+}
+
+// Verify the merkle proof with whichever scheme the tree that produced it uses
+pub fn verify_merkle_proof(leaf: &[u8; 32], proof: &MerkleProof, root: &[u8; 32]) -> bool {
+    proof.verify(leaf, root)
+}
+
+const SPENDING_KEY_DOMAIN: &[u8] = b"IntelliFi spending key";
+const NULLIFIER_DOMAIN: &[u8] = b"IntelliFi nullifier";
+
+// Public handle on a spending key, stored in the note's leaf
+pub fn spending_key_hash(spending_key: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(SPENDING_KEY_DOMAIN);
+    hasher.update(spending_key);
+    hasher.finalize().into()
+}
+
+// The one nullifier a note has on a deployment: only the spending key holder
+// can compute it, and it is fixed by the note's leaf
+pub fn derive_nullifier(domain: &SpendDomain, spending_key: &[u8; 32], leaf: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(NULLIFIER_DOMAIN);
+    hasher.update(domain.chain_id.to_be_bytes());
+    hasher.update(domain.verifier_address);
+    hasher.update(spending_key);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+// Verify the note's nullifier is the one derived from its spending key and leaf
+pub fn verify_nullifier(domain: &SpendDomain, note: &SpendNoteInput, leaf: &[u8; 32]) -> bool {
+    derive_nullifier(domain, &note.nullifier_data.spending_key, leaf) == note.nullifier
+}
+
+// Commit to `amount` with `blinding_factor`, None if the blinding factor is not a canonical scalar
+pub fn amount_commitment(amount: u64, blinding_factor: [u8; 32]) -> Option<AmountCommitment> {
+    Some(AmountCommitment {
+        commitment: pedersen::commit(amount, &blinding_factor)?,
+        amount,
+        blinding_factor,
+    })
+}
+
+// Verify amount commitment using Pedersen commitment scheme
+pub fn verify_amount_commitment(commitment: &AmountCommitment, expected_amount: u64) -> bool {
+    pedersen::verify(&commitment.commitment, expected_amount, &commitment.blinding_factor)
+}
+
+// Bit width every hidden amount is proven within. It is fixed here, not chosen
+// by the prover, since the journal doesn't commit to it and a prover free to
+// pick 64 would turn the check off.
+pub const AMOUNT_RANGE_BITS: u32 = 63;
+
+// Check `amount` is below 2^AMOUNT_RANGE_BITS
+pub fn verify_range(amount: u64) -> bool {
+    amount >> AMOUNT_RANGE_BITS == 0
+}
+
+// Verify the note amount is in range and splits exactly into the outputs and the
+// relayer fee, returning the commitments to publish
+pub fn verify_hidden_amount(
+    note: &AmountCommitment,
+    outputs: &[AmountCommitment],
+    relayer_fee: u64,
+) -> Option<AmountDisclosure> {
+    if !verify_amount_commitment(note, note.amount) || !verify_range(note.amount) {
+        return None;
+    }
+
+    let mut total = relayer_fee;
+    for output in outputs {
+        if !verify_amount_commitment(output, output.amount) || !verify_range(output.amount) {
+            return None;
+        }
+        total = total.checked_add(output.amount)?;
+    }
+    if total != note.amount {
+        return None;
+    }
+
+    Some(AmountDisclosure::Hidden {
+        note_commitment: note.commitment,
+        outputs: outputs.iter().map(|o| o.commitment).collect(),
+    })
+}
+
+// Compute leaf hash for merkle tree. The nullifier is derived from the leaf,
+// so the leaf commits to the spending key through its hash instead.
+pub fn compute_leaf_hash(note: &SpendNoteInput) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(note.wallet_address);
+
+    // Hash the Pedersen commitment components
+    hasher.update(note.amount_commitment.commitment);
+    hasher.update(note.amount_commitment.blinding_factor);
+
+    // Bind the note to its spending key
+    hasher.update(spending_key_hash(&note.nullifier_data.spending_key));
+
+    hasher.finalize().into()
+}
+
+impl Note {
+    // The leaf inserted for this note at deposit time, None if its blinding
+    // factor is not a canonical scalar
+    pub fn leaf_hash(&self) -> Option<[u8; 32]> {
+        Some(compute_leaf_hash(&self.without_nullifier()?))
+    }
+
+    // The note as the guest takes it, with its nullifier on `domain`
+    pub fn to_spend_note(&self, domain: &SpendDomain) -> Option<SpendNoteInput> {
+        let mut spend_note = self.without_nullifier()?;
+        let leaf = compute_leaf_hash(&spend_note);
+        spend_note.nullifier = derive_nullifier(domain, &self.nullifier_data.spending_key, &leaf);
+        Some(spend_note)
+    }
+
+    fn without_nullifier(&self) -> Option<SpendNoteInput> {
+        Some(SpendNoteInput {
+            wallet_address: self.wallet_address,
+            nullifier: [0; 32],
+            amount_commitment: amount_commitment(self.amount, self.blinding_factor)?,
+            nullifier_data: self.nullifier_data.clone(),
+        })
+    }
+}
+
+// Fixtures shared by the tests of every module
+#[cfg(test)]
+pub(crate) mod testing {
+    use k256::ecdsa::SigningKey;
+    use sha3::{Digest, Keccak256};
+
+    use crate::auth::eth_signed_message_hash;
+    use crate::SpendAuthorization;
+
+    // The key test notes are owned by, and its wallet address
+    pub fn owner() -> (SigningKey, [u8; 20]) {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let point = key.verifying_key().to_encoded_point(false);
+        let mut wallet = [0u8; 20];
+        wallet.copy_from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..]);
+        (key, wallet)
+    }
+
+    // `key`'s personal_sign authorization of a spend message
+    pub fn sign(key: &SigningKey, message: &[u8; 32]) -> SpendAuthorization {
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&eth_signed_message_hash(message)).unwrap();
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+        SpendAuthorization { signature }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::{owner, sign};
+    use alloc::vec;
+
+    const DOMAIN: SpendDomain = SpendDomain {
+        chain_id: 11155111,
+        verifier_address: [3; 20],
+    };
+
+    fn blinding(seed: u8) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0] = seed;
+        bytes
+    }
+
+    fn note(amount: u64) -> SpendNoteInput {
+        Note {
+            wallet_address: [1; 20],
+            amount,
+            nullifier_data: NullifierData { spending_key: [7; 32] },
+            blinding_factor: blinding(9),
+        }
+        .to_spend_note(&DOMAIN)
+        .unwrap()
+    }
+
+    #[test]
+    fn commitments_open_only_to_their_amount() {
+        let commitment = amount_commitment(100, blinding(5)).unwrap();
+        assert!(verify_amount_commitment(&commitment, 100));
+        assert!(!verify_amount_commitment(&commitment, 101));
+        // Scalars must be reduced
+        assert_eq!(amount_commitment(100, [0xff; 32]), None);
+    }
+
+    #[test]
+    fn nullifier_is_bound_to_leaf_and_domain() {
+        let spend_note = note(100);
+        let leaf = compute_leaf_hash(&spend_note);
+        assert!(verify_nullifier(&DOMAIN, &spend_note, &leaf));
+        assert!(!verify_nullifier(&DOMAIN, &spend_note, &[0; 32]));
+
+        let other = SpendDomain { chain_id: 1, ..DOMAIN };
+        assert!(!verify_nullifier(&other, &spend_note, &leaf));
+    }
+
+    #[test]
+    fn hidden_amount_must_balance() {
+        let spend_note = amount_commitment(100, blinding(1)).unwrap();
+        let outputs = vec![
+            amount_commitment(60, blinding(2)).unwrap(),
+            amount_commitment(30, blinding(3)).unwrap(),
+        ];
+        let disclosure = verify_hidden_amount(&spend_note, &outputs, 10).unwrap();
+        assert_eq!(
+            disclosure,
+            AmountDisclosure::Hidden {
+                note_commitment: spend_note.commitment,
+                outputs: vec![outputs[0].commitment, outputs[1].commitment],
+            }
+        );
+        assert_eq!(verify_hidden_amount(&spend_note, &outputs, 11), None);
+    }
+
+    #[test]
+    fn hidden_amounts_must_be_in_range() {
+        let top = 1u64 << AMOUNT_RANGE_BITS;
+        assert!(verify_range(top - 1));
+        assert!(!verify_range(top));
+        assert!(!verify_range(u64::MAX));
+
+        // Balanced, but the note is out of range
+        let spend_note = amount_commitment(top, blinding(1)).unwrap();
+        let outputs = vec![
+            amount_commitment(top - 1, blinding(2)).unwrap(),
+            amount_commitment(1, blinding(3)).unwrap(),
+        ];
+        assert_eq!(verify_hidden_amount(&spend_note, &outputs, 0), None);
+
+        // In range, but an output is not
+        let spend_note = amount_commitment(top - 1, blinding(1)).unwrap();
+        let outputs = vec![amount_commitment(top, blinding(2)).unwrap()];
+        assert_eq!(verify_hidden_amount(&spend_note, &outputs, 0), None);
+    }
+
+    #[test]
+    fn authorization_recovers_the_owner() {
+        let (key, wallet) = owner();
+        let message = auth::spend_message_hash(&wallet, &[1; 32], &[2; 32], 100, &[9; 20], 0, &DOMAIN);
+        let authorization = sign(&key, &message);
+
+        assert!(auth::verify_authorization(&wallet, &authorization, &message));
+        assert!(!auth::verify_authorization(&[0; 20], &authorization, &message));
+    }
+
+    #[test]
+    fn verify_spend_checks_the_whole_input() {
+        let (key, wallet) = owner();
+
+        let owned = Note {
+            wallet_address: wallet,
+            amount: 100,
+            nullifier_data: NullifierData { spending_key: [7; 32] },
+            blinding_factor: blinding(9),
+        };
+        let leaf = owned.leaf_hash().unwrap();
+        let spend_note = owned.to_spend_note(&DOMAIN).unwrap();
+        let tree = MerkleTree::new(MerkleScheme::SortedSha256, vec![leaf, [4; 32]]);
+        let root = tree.root();
+
+        let message = auth::spend_message_hash(&wallet, &spend_note.nullifier, &root, 100, &[9; 20], 1, &DOMAIN);
+
+        let mut input = SpendVerificationInput {
+            spend_note,
+            merkle_proof: tree.proof(0).unwrap(),
+            merkle_root: root,
+            amount_mode: AmountMode::Reveal { expected_amount: 100 },
+            authorization: sign(&key, &message),
+            recipient: [9; 20],
+            relayer_fee: 1,
+            domain: DOMAIN,
+        };
+        assert_eq!(verify_spend(&input), Ok(AmountDisclosure::Revealed(100)));
+
+        input.recipient = [8; 20];
+        assert_eq!(verify_spend(&input), Err(VerificationStatus::InvalidAuthorization));
+        input.recipient = [9; 20];
+        input.merkle_root = [0; 32];
+        assert_eq!(verify_spend(&input), Err(VerificationStatus::InvalidAuthorization));
+        input.merkle_root = root;
+        input.merkle_proof.path[0] = [5; 32];
+        assert_eq!(verify_spend(&input), Err(VerificationStatus::InvalidMerklePath));
+
+        // The signature names the note by its nullifier, so it doesn't cover
+        // another of the owner's notes of the same amount in the same tree
+        let other = Note {
+            nullifier_data: NullifierData { spending_key: [8; 32] },
+            ..owned
+        };
+        let tree = MerkleTree::new(MerkleScheme::SortedSha256, vec![leaf, other.leaf_hash().unwrap()]);
+        input.spend_note = other.to_spend_note(&DOMAIN).unwrap();
+        input.merkle_proof = tree.proof(1).unwrap();
+        input.merkle_root = tree.root();
+        assert_eq!(verify_spend(&input), Err(VerificationStatus::InvalidAuthorization));
+    }
+
+    #[test]
+    fn canonical_bytes_round_trip() {
+        let input = SpendVerificationInput {
+            spend_note: note(100),
+            merkle_proof: MerkleProof {
+                scheme: MerkleScheme::SortedSha256,
+                path: vec![[4; 32]],
+                indices: vec![true],
+            },
+            merkle_root: [5; 32],
+            amount_mode: AmountMode::Reveal { expected_amount: 100 },
+            authorization: SpendAuthorization { signature: vec![1; 65] },
+            recipient: [9; 20],
+            relayer_fee: 1,
+            domain: DOMAIN,
+        };
+        let bytes = to_canonical_bytes(&input).unwrap();
+        assert_eq!(from_canonical_bytes::<SpendVerificationInput>(&bytes).unwrap(), input);
+        assert!(from_canonical_bytes::<SpendVerificationInput>(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
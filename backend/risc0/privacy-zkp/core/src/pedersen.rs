@@ -0,0 +1,32 @@
+// Pedersen commitments over Ristretto, exchanged as compressed bytes
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+
+// Generator points (in a real implementation these would be generated through a trusted setup)
+pub const H: RistrettoPoint = RISTRETTO_BASEPOINT_POINT;
+
+// G is the hash of "G", so nobody knows its discrete log relative to H
+pub fn generator_g() -> RistrettoPoint {
+    let mut hasher = Sha512::new();
+    hasher.update(b"G");
+    let hash: [u8; 64] = hasher.finalize().into();
+    RistrettoPoint::from_uniform_bytes(&hash)
+}
+
+// Commit to `amount` with a blinding factor, None if it is not a canonical scalar
+pub fn commit(amount: u64, blinding_factor: &[u8; 32]) -> Option<[u8; 32]> {
+    let blinding_factor = Option::<Scalar>::from(Scalar::from_canonical_bytes(*blinding_factor))?;
+    let point = generator_g() * Scalar::from(amount) + H * blinding_factor;
+    Some(point.compress().to_bytes())
+}
+
+// Whether `commitment` opens to `amount` with `blinding_factor`
+pub fn verify(commitment: &[u8; 32], amount: u64, blinding_factor: &[u8; 32]) -> bool {
+    // Reject encodings that don't decompress, so every commitment has one byte form
+    if CompressedRistretto(*commitment).decompress().is_none() {
+        return false;
+    }
+    commit(amount, blinding_factor).as_ref() == Some(commitment)
+}
@@ -0,0 +1,175 @@
+// Spend types shared by the guest, host, drew-v and the napi addon. Everything
+// is plain bytes so each consumer can build them without curve types.
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+pub use privacy_zkp_merkle::{MerkleProof, MerkleScheme};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NullifierData {
+    pub spending_key: [u8; 32],    // Secret known only to the note owner; its hash is in the leaf
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountCommitment {
+    pub commitment: [u8; 32],      // Compressed Ristretto Pedersen commitment
+    pub amount: u64,               // Actual amount (will be hidden)
+    pub blinding_factor: [u8; 32], // Canonical little-endian scalar
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendNoteInput {
+    pub wallet_address: [u8; 20],  // ETH address is 20 bytes
+    pub nullifier: [u8; 32],       // derive_nullifier(domain, spending_key, leaf)
+    pub amount_commitment: AmountCommitment,
+    pub nullifier_data: NullifierData,
+}
+
+// A note as its owner keeps it from deposit time; its leaf and nullifier are
+// rebuilt from these values when it is spent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub wallet_address: [u8; 20],
+    pub amount: u64,
+    pub nullifier_data: NullifierData,
+    pub blinding_factor: [u8; 32], // Of the note's amount commitment
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendAuthorization {
+    pub signature: Vec<u8>,        // 65-byte personal_sign over auth::spend_message_hash
+}
+
+// The FaceVerifier deployment a spend is valid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendDomain {
+    pub chain_id: u64,
+    pub verifier_address: [u8; 20], // FaceVerifier contract address
+}
+
+// How the spent amount is proven
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmountMode {
+    // The commitment opens to `expected_amount`, which is committed in the clear
+    Reveal { expected_amount: u64 },
+    // The note and output amounts are below 2^AMOUNT_RANGE_BITS and the note
+    // equals the outputs plus the relayer fee; only commitments reach the journal
+    Hidden { outputs: Vec<AmountCommitment> },
+}
+
+// What the journal says about the amount
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AmountDisclosure {
+    Revealed(u64),
+    Hidden { note_commitment: [u8; 32], outputs: Vec<[u8; 32]> },
+}
+
+// Outcome of the guest's checks, committed first in every journal. A rejected
+// spend commits only its status, so the host can tell why instead of seeing a
+// failed proof. Discriminants are stable; append new codes at the end.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationStatus {
+    Valid = 0,
+    InvalidNullifier = 1,
+    InvalidCommitment = 2,
+    InvalidMerklePath = 3,
+    InvalidAuthorization = 4,
+    FeeExceedsAmount = 5,
+    DuplicateNullifier = 6,
+    EmptyBatch = 7,
+    AmountOverflow = 8,
+}
+
+impl VerificationStatus {
+    pub fn is_valid(self) -> bool {
+        self == Self::Valid
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendVerificationInput {
+    pub spend_note: SpendNoteInput,
+    pub merkle_proof: MerkleProof,
+    pub merkle_root: [u8; 32],
+    pub amount_mode: AmountMode,   // Reveal the amount, or prove it in range and balanced
+    pub authorization: SpendAuthorization, // Owner's signature instead of their private key
+    pub recipient: [u8; 20],       // Address the funds are released to, committed to the journal
+    pub relayer_fee: u64,          // Part of the amount paid to the submitter, 0 if none
+    pub domain: SpendDomain,       // Chain and contract the proof may be used on
+}
+
+// One note of a batch spend; every note is checked against the batch's root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchNote {
+    pub spend_note: SpendNoteInput,
+    pub merkle_proof: MerkleProof,
+    pub expected_amount: u64,
+    pub authorization: SpendAuthorization, // Signs this note's amount with the batch recipient and fee
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchSpendInput {
+    pub notes: Vec<BatchNote>,
+    pub merkle_root: [u8; 32],
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,          // Taken once from the total
+    pub domain: SpendDomain,
+}
+
+// Version of the spend and batch journal layouts; bump it whenever a journal
+// struct or the header changes, and keep the old layouts decodable.
+//
+// Receipts from before versioning committed only (merkle_root, nullifier,
+// amount), with no recipient or deployment to bind them to, so they are not
+// decoded at all: accepting one would let it be replayed anywhere.
+pub const JOURNAL_VERSION: u32 = 2;
+// First word of every versioned journal. Unversioned journals open with the
+// first byte of their merkle root, one word per byte, so they can't match.
+pub const JOURNAL_MAGIC: u32 = u32::from_be_bytes(*b"IFSJ");
+
+// Committed first by the spend and batch guests; a rejected spend commits
+// nothing after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalHeader {
+    pub magic: u32,
+    pub version: u32,
+    pub status: VerificationStatus,
+}
+
+impl JournalHeader {
+    pub fn new(status: VerificationStatus) -> Self {
+        Self { magic: JOURNAL_MAGIC, version: JOURNAL_VERSION, status }
+    }
+
+    // Whether a journal opening with `first_word` has a header
+    pub fn is_versioned(first_word: u32) -> bool {
+        first_word == JOURNAL_MAGIC
+    }
+}
+
+// Public outputs of an accepted spend, committed once after the header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendJournal {
+    pub merkle_root: [u8; 32],     // To verify against on-chain state
+    pub nullifier: [u8; 32],       // To prevent double-spending
+    pub disclosure: AmountDisclosure, // Amount, or only its commitments in hidden mode
+    pub recipient: [u8; 20],       // So the receipt can't be replayed to another address
+    pub relayer_fee: u64,
+    pub domain: SpendDomain,       // So the receipt can't be replayed on another deployment
+}
+
+// Public outputs of an accepted batch spend, committed once after the header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchJournal {
+    pub merkle_root: [u8; 32],     // Shared by every note
+    pub nullifiers: Vec<[u8; 32]>, // In input order, so each can be marked spent
+    pub total_amount: u64,
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,
+    pub domain: SpendDomain,
+}
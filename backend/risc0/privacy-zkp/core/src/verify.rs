@@ -0,0 +1,244 @@
+// The checks the spend and batch guests run, in the order they run them. The
+// guests commit what these return; the host, drew-v and the napi addon call
+// them to catch a spend the guest would reject before proving it.
+use alloc::vec::Vec;
+
+use crate::auth::{spend_message_hash, verify_authorization};
+use crate::{
+    compute_leaf_hash, verify_amount_commitment, verify_hidden_amount, verify_merkle_proof,
+    verify_nullifier, AmountDisclosure, AmountMode, BatchSpendInput, SpendVerificationInput,
+    VerificationStatus,
+};
+
+// Run every check on the spend, returning what the journal discloses about the amount
+pub fn verify_spend(input: &SpendVerificationInput) -> Result<AmountDisclosure, VerificationStatus> {
+    // 1. Verify the nullifier is the one derived from the spending key and leaf
+    let leaf_hash = compute_leaf_hash(&input.spend_note);
+    let is_nullifier_valid = verify_nullifier(
+        &input.domain,
+        &input.spend_note,
+        &leaf_hash
+    );
+    if !is_nullifier_valid {
+        return Err(VerificationStatus::InvalidNullifier);
+    }
+
+    // 2. Verify the amount commitment using Pedersen commitment scheme
+    let note_amount = &input.spend_note.amount_commitment;
+    let (amount, disclosure) = match &input.amount_mode {
+        AmountMode::Reveal { expected_amount } => {
+            if !verify_amount_commitment(note_amount, *expected_amount) {
+                return Err(VerificationStatus::InvalidCommitment);
+            }
+            (*expected_amount, AmountDisclosure::Revealed(*expected_amount))
+        }
+        AmountMode::Hidden { outputs } => {
+            let disclosure = verify_hidden_amount(note_amount, outputs, input.relayer_fee)
+                .ok_or(VerificationStatus::InvalidCommitment)?;
+            (note_amount.amount, disclosure)
+        }
+    };
+
+    // 3. Verify the note owner authorized this spend, to this recipient and fee
+    if input.relayer_fee > amount {
+        return Err(VerificationStatus::FeeExceedsAmount);
+    }
+    let message_hash = spend_message_hash(
+        &input.spend_note.wallet_address,
+        &input.spend_note.nullifier,
+        &input.merkle_root,
+        amount,
+        &input.recipient,
+        input.relayer_fee,
+        &input.domain
+    );
+    let is_authorized = verify_authorization(
+        &input.spend_note.wallet_address,
+        &input.authorization,
+        &message_hash
+    );
+    if !is_authorized {
+        return Err(VerificationStatus::InvalidAuthorization);
+    }
+
+    // 4. Verify the merkle proof (proves the note is in the tree; whether its
+    // nullifier was already used is checked on-chain)
+    let is_proof_valid = verify_merkle_proof(
+        &leaf_hash,
+        &input.merkle_proof,
+        &input.merkle_root
+    );
+    if !is_proof_valid {
+        return Err(VerificationStatus::InvalidMerklePath);
+    }
+
+    Ok(disclosure)
+}
+
+// Check every note of the batch, returning their nullifiers and total amount
+pub fn verify_batch(input: &BatchSpendInput) -> Result<(Vec<[u8; 32]>, u64), VerificationStatus> {
+    if input.notes.is_empty() {
+        return Err(VerificationStatus::EmptyBatch);
+    }
+
+    let mut nullifiers: Vec<[u8; 32]> = Vec::with_capacity(input.notes.len());
+    let mut total_amount: u64 = 0;
+
+    for note in &input.notes {
+        let spend_note = &note.spend_note;
+
+        // 1. Verify the nullifier is the one derived for this note, and appears once in the batch
+        let leaf_hash = compute_leaf_hash(spend_note);
+        let is_nullifier_valid = verify_nullifier(
+            &input.domain,
+            spend_note,
+            &leaf_hash
+        );
+        if !is_nullifier_valid {
+            return Err(VerificationStatus::InvalidNullifier);
+        }
+        if nullifiers.contains(&spend_note.nullifier) {
+            return Err(VerificationStatus::DuplicateNullifier);
+        }
+
+        // 2. Verify the amount commitment using Pedersen commitment scheme
+        if !verify_amount_commitment(&spend_note.amount_commitment, note.expected_amount) {
+            return Err(VerificationStatus::InvalidCommitment);
+        }
+        total_amount = total_amount
+            .checked_add(note.expected_amount)
+            .ok_or(VerificationStatus::AmountOverflow)?;
+
+        // 3. Verify the note owner authorized this spend, to this recipient and fee
+        let message_hash = spend_message_hash(
+            &spend_note.wallet_address,
+            &spend_note.nullifier,
+            &input.merkle_root,
+            note.expected_amount,
+            &input.recipient,
+            input.relayer_fee,
+            &input.domain
+        );
+        if !verify_authorization(&spend_note.wallet_address, &note.authorization, &message_hash) {
+            return Err(VerificationStatus::InvalidAuthorization);
+        }
+
+        // 4. Verify the merkle proof against the shared root
+        if !verify_merkle_proof(&leaf_hash, &note.merkle_proof, &input.merkle_root) {
+            return Err(VerificationStatus::InvalidMerklePath);
+        }
+
+        nullifiers.push(spend_note.nullifier);
+    }
+
+    if input.relayer_fee > total_amount {
+        return Err(VerificationStatus::FeeExceedsAmount);
+    }
+
+    Ok((nullifiers, total_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{owner, sign};
+    use crate::{BatchNote, MerkleScheme, MerkleTree, Note, NullifierData, SpendDomain};
+
+    const DOMAIN: SpendDomain = SpendDomain {
+        chain_id: 11155111,
+        verifier_address: [3; 20],
+    };
+    const RECIPIENT: [u8; 20] = [9; 20];
+
+    fn note(seed: u8, amount: u64) -> Note {
+        let mut blinding_factor = [0u8; 32];
+        blinding_factor[0] = seed;
+        Note {
+            wallet_address: owner().1,
+            amount,
+            nullifier_data: NullifierData { spending_key: [seed; 32] },
+            blinding_factor,
+        }
+    }
+
+    // A batch spend of `notes` to RECIPIENT, each signed by the owner and
+    // proven against one tree holding all of them
+    fn batch(notes: &[Note], relayer_fee: u64) -> BatchSpendInput {
+        let (key, _) = owner();
+        let tree = MerkleTree::new(
+            MerkleScheme::SortedSha256,
+            notes.iter().map(|n| n.leaf_hash().unwrap()).collect(),
+        );
+        let merkle_root = tree.root();
+
+        let notes = notes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                let spend_note = n.to_spend_note(&DOMAIN).unwrap();
+                let message = spend_message_hash(
+                    &n.wallet_address,
+                    &spend_note.nullifier,
+                    &merkle_root,
+                    n.amount,
+                    &RECIPIENT,
+                    relayer_fee,
+                    &DOMAIN,
+                );
+                BatchNote {
+                    spend_note,
+                    merkle_proof: tree.proof(i).unwrap(),
+                    expected_amount: n.amount,
+                    authorization: sign(&key, &message),
+                }
+            })
+            .collect();
+
+        BatchSpendInput {
+            notes,
+            merkle_root,
+            recipient: RECIPIENT,
+            relayer_fee,
+            domain: DOMAIN,
+        }
+    }
+
+    #[test]
+    fn batch_totals_its_notes() {
+        let notes = [note(1, 100), note(2, 200)];
+        let nullifiers: Vec<_> = notes
+            .iter()
+            .map(|n| n.to_spend_note(&DOMAIN).unwrap().nullifier)
+            .collect();
+        assert_eq!(verify_batch(&batch(&notes, 300)), Ok((nullifiers, 300)));
+    }
+
+    #[test]
+    fn batch_rejections() {
+        let mut empty = batch(&[note(1, 100)], 0);
+        empty.notes.clear();
+        assert_eq!(verify_batch(&empty), Err(VerificationStatus::EmptyBatch));
+
+        // The same note twice, each copy with a valid path and signature
+        assert_eq!(
+            verify_batch(&batch(&[note(1, 100), note(1, 100)], 0)),
+            Err(VerificationStatus::DuplicateNullifier)
+        );
+
+        assert_eq!(
+            verify_batch(&batch(&[note(1, u64::MAX), note(2, 1)], 0)),
+            Err(VerificationStatus::AmountOverflow)
+        );
+
+        // The fee comes out of the total once, not out of each note
+        assert_eq!(
+            verify_batch(&batch(&[note(1, 100), note(2, 200)], 301)),
+            Err(VerificationStatus::FeeExceedsAmount)
+        );
+
+        // One bad path fails the batch even though the others are fine
+        let mut input = batch(&[note(1, 100), note(2, 200), note(3, 300)], 0);
+        input.notes[1].merkle_proof.path[0] = [5; 32];
+        assert_eq!(verify_batch(&input), Err(VerificationStatus::InvalidMerklePath));
+    }
+}
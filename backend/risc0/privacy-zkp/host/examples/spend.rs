@@ -1,44 +1,95 @@
-use host::{ProofGenerator, ProofVerifier, MerkleProof};
-use sha2::{Digest, Sha256};
+use privacy_zkp_host::{
+    eth_signed_message_hash, new_note, spend_message_hash, MerkleScheme, MerkleTree, ProofGenerator,
+    ProofVerifier, SpendAuthorization, SpendDomain, SpendError, SpendIntent,
+};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
 
 fn main() {
-    // Example wallet address (20 bytes)
-    let wallet_address = [1u8; 20];
+    // Example wallet key; in practice this stays in the user's wallet
+    let wallet_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    
+    // Wallet address derived from the key (20 bytes)
+    let public_key = wallet_key.verifying_key().to_encoded_point(false);
+    let mut wallet_address = [0u8; 20];
+    wallet_address.copy_from_slice(&Keccak256::digest(&public_key.as_bytes()[1..])[12..]);
     
     // Example amount
     let amount = 1_000_000; // 1 ETH in wei
     
-    // Example merkle proof (simplified for demo)
-    let merkle_proof = MerkleProof {
-        path: vec![[0u8; 32], [1u8; 32], [2u8; 32]], // Example path
-        indices: vec![true, false, true],             // Example indices
+    // The note as created at deposit time; the owner keeps its secrets to spend it
+    let note = new_note(wallet_address, amount);
+    
+    // Example tree holding the note's leaf next to another deposit
+    let tree = MerkleTree::new(
+        MerkleScheme::SortedSha256, // Same hashing as the TypeScript tree
+        vec![[0u8; 32], note.leaf_hash().unwrap()],
+    );
+    let merkle_proof = tree.proof(1).unwrap();
+    let merkle_root = tree.root();
+    
+    // The deployment the proof is for (Sepolia here)
+    let domain = SpendDomain {
+        chain_id: 11155111,
+        verifier_address: [3u8; 20],
+    };
+    
+    // Where the funds go; no relayer, and the amount is revealed in this example
+    let intent = SpendIntent {
+        recipient: [9u8; 20],
+        relayer_fee: 0,
+        hidden: None,
     };
     
-    // Example merkle root
-    let merkle_root = [42u8; 32];
+    // The wallet signs the spend of this note instead of handing over its key
+    let nullifier = note.to_spend_note(&domain).unwrap().nullifier;
+    let message_hash = spend_message_hash(
+        &wallet_address,
+        &nullifier,
+        &merkle_root,
+        amount,
+        &intent.recipient,
+        intent.relayer_fee,
+        &domain,
+    );
+    let (signature, recovery_id) = wallet_key
+        .sign_prehash_recoverable(&eth_signed_message_hash(&message_hash))
+        .unwrap();
+    let mut signature = signature.to_bytes().to_vec();
+    signature.push(recovery_id.to_byte() + 27);
+    let authorization = SpendAuthorization { signature };
     
     println!("Generating proof...");
     
     // Create proof generator
-    let generator = ProofGenerator::new();
+    let generator = ProofGenerator::new(domain);
     
     // Generate the proof
-    let proof = generator.prove_spend(
-        wallet_address,
-        amount,
+    let proof = match generator.prove_spend(
+        &note,
+        authorization,
+        intent.clone(),
         merkle_proof,
         merkle_root,
-    ).expect("Failed to generate proof");
+    ) {
+        Ok(proof) => proof,
+        Err(SpendError::Rejected(status)) => {
+            println!("Spend rejected: {:?} (code {})", status, status.code());
+            return;
+        }
+        Err(e) => panic!("Failed to generate proof: {}", e),
+    };
     
     println!("Proof generated successfully!");
     println!("Merkle root: 0x{}", hex::encode(proof.merkle_root));
     println!("Nullifier: 0x{}", hex::encode(proof.nullifier));
-    println!("Amount: {}", proof.amount);
+    println!("Amount: {:?}", proof.amount);
+    println!("Recipient: 0x{}", hex::encode(proof.recipient));
     
     println!("\nVerifying proof...");
     
-    // Verify the proof
-    match ProofVerifier::verify_spend(&proof) {
+    // Verify the proof against the transaction we would submit
+    match ProofVerifier::verify_spend_for(&proof, &intent, &domain) {
         Ok(true) => println!("Proof verified successfully!"),
         Ok(false) => println!("Proof verification failed!"),
         Err(e) => println!("Error verifying proof: {}", e),
@@ -0,0 +1,282 @@
+// Receipts on disk, addressed by what was proven, so a retried request or a
+// replayed event gets the receipt it already paid for instead of a new proof.
+//
+// The key is the SHA-256 of the guest's image ID, whether the receipt was
+// compressed, and the hash of the guest input's canonical bytes. The same
+// input to the same guest always proves the same journal, so any receipt
+// under a key is as good as a fresh one. Each entry is the key's hash followed
+// by a receipt archive (see `export`). When it is loaded the hash must be the
+// one it was looked up by, so a renamed or swapped file isn't taken for another
+// spend's proof, and the archive is checked and verified against the key's
+// image ID; an entry that fails any of these is deleted and treated as a miss.
+//
+// Entries are evicted least recently used first, by file modification time,
+// which `get` refreshes, once the cache holds more than `max_entries` or
+// `max_bytes`.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use privacy_zkp_core::to_canonical_bytes;
+use risc0_zkvm::{Digest, Receipt};
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::export::{from_archive, to_archive, ExportedReceipt};
+
+const KEY_DOMAIN: &[u8] = b"IntelliFi receipt cache v1";
+const ENTRY_EXTENSION: &str = "receipt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheKey {
+    image_id: Digest,
+    hash: [u8; 32],
+}
+
+impl CacheKey {
+    pub fn new<T: Serialize>(image_id: impl Into<Digest>, input: &T, compressed: bool) -> Result<Self, String> {
+        let image_id = image_id.into();
+        let input = to_canonical_bytes(input).map_err(|e| e.to_string())?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_DOMAIN);
+        hasher.update(image_id.as_bytes());
+        hasher.update([compressed as u8]);
+        hasher.update(Sha256::digest(&input));
+        Ok(Self { image_id, hash: hasher.finalize().into() })
+    }
+
+    pub fn image_id(&self) -> Digest {
+        self.image_id
+    }
+
+    pub fn hex(&self) -> String {
+        hex::encode(self.hash)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self { max_entries: 256, max_bytes: 1 << 30 }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReceiptCache {
+    dir: PathBuf,
+    policy: CachePolicy,
+}
+
+impl ReceiptCache {
+    // Use `dir` for the cache, creating it if needed
+    pub fn open(dir: impl Into<PathBuf>, policy: CachePolicy) -> Result<Self, String> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        Ok(Self { dir, policy })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // The receipt stored under `key`, if there is one that still verifies
+    pub fn get(&self, key: &CacheKey) -> Option<Receipt> {
+        let path = self.entry_path(key);
+        let contents = fs::read(&path).ok()?;
+        let archive = contents.strip_prefix(&key.hash[..]);
+        let entry = match archive.map(from_archive) {
+            Some(Ok(entry)) if entry.image_id == key.image_id && entry.verify().is_ok() => entry,
+            _ => {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        // Mark the entry used; eviction goes by modification time
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(entry.receipt)
+    }
+
+    // Store `receipt` under `key`, then evict down to the policy
+    pub fn put(&self, key: &CacheKey, receipt: &Receipt) -> Result<(), String> {
+        let mut entry = key.hash.to_vec();
+        entry.extend(to_archive(&ExportedReceipt::new(key.image_id, receipt.clone()))?);
+
+        // Write under a name no other process uses, then move it into place,
+        // so readers never see a partial entry
+        let path = self.entry_path(key);
+        let temp = path.with_extension(format!("{}.tmp", rand::random::<u64>()));
+        fs::write(&temp, entry).map_err(|e| format!("Failed to write cache entry: {}", e))?;
+        fs::rename(&temp, &path).map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            format!("Failed to write cache entry: {}", e)
+        })?;
+
+        self.evict()
+    }
+
+    // Remove least recently used entries until the cache is within its policy
+    fn evict(&self) -> Result<(), String> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read cache directory: {}", e))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != ENTRY_EXTENSION {
+                    return None;
+                }
+                let metadata = fs::metadata(&path).ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), path))
+            })
+            .collect();
+        entries.sort();
+
+        let mut count = entries.len();
+        let mut bytes: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in entries {
+            if count <= self.policy.max_entries && bytes <= self.policy.max_bytes {
+                break;
+            }
+            // Another process may have evicted it already
+            let _ = fs::remove_file(&path);
+            count -= 1;
+            bytes -= len;
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(key.hex()).with_extension(ENTRY_EXTENSION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+    use std::time::Duration;
+
+    #[test]
+    fn keys_cover_image_compression_and_input() {
+        let key = CacheKey::new([1u32; 8], &(7u64, [2u8; 32]), false).unwrap();
+        assert_eq!(key, CacheKey::new([1u32; 8], &(7u64, [2u8; 32]), false).unwrap());
+        assert_ne!(key, CacheKey::new([2u32; 8], &(7u64, [2u8; 32]), false).unwrap());
+        assert_ne!(key, CacheKey::new([1u32; 8], &(7u64, [2u8; 32]), true).unwrap());
+        assert_ne!(key, CacheKey::new([1u32; 8], &(8u64, [2u8; 32]), false).unwrap());
+    }
+
+    #[test]
+    fn eviction_removes_least_recently_used_entries() {
+        let dir = std::env::temp_dir().join(format!("receipt-cache-{}", rand::random::<u64>()));
+        let cache = ReceiptCache::open(&dir, CachePolicy { max_entries: 10, max_bytes: 25 }).unwrap();
+
+        let now = SystemTime::now();
+        for (name, age) in [("a", 3), ("b", 2), ("c", 1)] {
+            let path = dir.join(name).with_extension(ENTRY_EXTENSION);
+            fs::write(&path, [0u8; 10]).unwrap();
+            File::options()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+        fs::write(dir.join("notes.txt"), [0u8; 100]).unwrap();
+
+        // Three 10 byte entries against a 25 byte budget: the oldest goes, and
+        // files that aren't entries are left alone
+        cache.evict().unwrap();
+        let mut left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["b.receipt", "c.receipt", "notes.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn temp_cache() -> ReceiptCache {
+        let dir = std::env::temp_dir().join(format!("receipt-cache-{}", rand::random::<u64>()));
+        ReceiptCache::open(dir, CachePolicy::default()).unwrap()
+    }
+
+    // Fake receipts verify only in dev mode
+    fn fake_receipt(image_id: Digest, journal: &[u8]) -> Receipt {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let claim = ReceiptClaim::ok(image_id, journal.to_vec());
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.to_vec())
+    }
+
+    fn assert_same(a: &Receipt, b: &Receipt) {
+        assert_eq!(bincode::serialize(a).unwrap(), bincode::serialize(b).unwrap());
+    }
+
+    #[test]
+    fn entries_are_found_under_their_key() {
+        let cache = temp_cache();
+        let image_id = Digest::from([7u32; 8]);
+        let receipt = fake_receipt(image_id, b"spend journal");
+        let key = CacheKey::new(image_id, &(7u64, [2u8; 32]), false).unwrap();
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &receipt).unwrap();
+        assert_same(&cache.get(&key).unwrap(), &receipt);
+
+        // The same input proven compressed is a different entry
+        assert!(cache.get(&CacheKey::new(image_id, &(7u64, [2u8; 32]), true).unwrap()).is_none());
+
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn swapped_entries_are_misses_and_deleted() {
+        let cache = temp_cache();
+        let image_id = Digest::from([7u32; 8]);
+        let key = CacheKey::new(image_id, &1u64, false).unwrap();
+        let other = CacheKey::new(image_id, &2u64, false).unwrap();
+        cache.put(&key, &fake_receipt(image_id, b"first spend")).unwrap();
+        cache.put(&other, &fake_receipt(image_id, b"second spend")).unwrap();
+
+        // The other spend's receipt verifies against the same guest, but it
+        // proves another input
+        fs::rename(cache.entry_path(&other), cache.entry_path(&key)).unwrap();
+        assert!(cache.get(&key).is_none());
+        assert!(!cache.entry_path(&key).exists());
+
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn corrupted_entries_are_misses_and_deleted() {
+        let cache = temp_cache();
+        let image_id = Digest::from([7u32; 8]);
+        let receipt = fake_receipt(image_id, b"spend journal");
+        let key = CacheKey::new(image_id, &(7u64, [2u8; 32]), false).unwrap();
+        cache.put(&key, &receipt).unwrap();
+
+        // The entry is the key's hash, then the receipt's archive under the
+        // key's image ID
+        let path = cache.entry_path(&key);
+        let mut archive = fs::read(&path).unwrap();
+        assert_eq!(archive[..32], key.hash);
+        let stored = from_archive(&archive[32..]).unwrap();
+        assert_eq!(stored.image_id, image_id);
+        assert_same(&stored.receipt, &receipt);
+
+        let middle = archive.len() / 2;
+        archive[middle] ^= 1;
+        fs::write(&path, &archive).unwrap();
+        assert!(cache.get(&key).is_none());
+        assert!(!path.exists());
+
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
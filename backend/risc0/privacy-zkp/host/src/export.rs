@@ -0,0 +1,347 @@
+// Encodings of a receipt for the places it is sent or stored:
+//
+// - SolidityProof: seal and journal for a RISC Zero Solidity verifier, which
+//   checks verify(seal, imageId, sha256(journal)). `abi_encode` gives
+//   abi.encode(bytes seal, bytes journal), the `_zkProof` argument of
+//   FaceVerifier.spendNoteWithProof.
+// - JSON envelope: the receipt with its image ID and format version, for the
+//   backend and anything else that stores receipts as text.
+// - Archive: a compact checksummed binary of the same, for long-term storage.
+//
+// Every encoder has a decoder that gives back what it was given.
+use risc0_zkvm::{Digest, InnerReceipt, Receipt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+// Format tag and version of the JSON envelope
+pub const ENVELOPE_FORMAT: &str = "intellifi-receipt";
+pub const ENVELOPE_VERSION: u32 = 1;
+
+// Archive layout: magic, version, image ID, receipt length (u32 big-endian),
+// bincode receipt, then the SHA-256 of everything before it
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"IFRC";
+pub const ARCHIVE_VERSION: u8 = 1;
+
+// A receipt with the image ID it should be verified against
+#[derive(Debug, Clone)]
+pub struct ExportedReceipt {
+    pub image_id: Digest,
+    pub receipt: Receipt,
+}
+
+impl ExportedReceipt {
+    pub fn new(image_id: impl Into<Digest>, receipt: Receipt) -> Self {
+        Self { image_id: image_id.into(), receipt }
+    }
+
+    // Verify the receipt against its image ID
+    pub fn verify(&self) -> Result<(), String> {
+        self.receipt.verify(self.image_id).map_err(|e| e.to_string())
+    }
+}
+
+// What a Solidity verifier needs to check a receipt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityProof {
+    // Verifier selector followed by the Groth16 seal
+    pub seal: Vec<u8>,
+    pub journal: Vec<u8>,
+}
+
+impl SolidityProof {
+    // Only Groth16 receipts can be checked on-chain; composite and succinct
+    // receipts have to be wrapped first
+    pub fn from_receipt(receipt: &Receipt) -> Result<Self, String> {
+        let seal = match &receipt.inner {
+            InnerReceipt::Groth16(inner) => {
+                // The verifier router picks the Groth16 verifier by the first
+                // four bytes of its parameters
+                let mut seal = inner.verifier_parameters.as_bytes()[..4].to_vec();
+                seal.extend_from_slice(&inner.seal);
+                seal
+            }
+            _ => return Err("Only Groth16 receipts can be verified on-chain".to_string()),
+        };
+
+        Ok(Self {
+            seal,
+            journal: receipt.journal.bytes.clone(),
+        })
+    }
+
+    // The journal digest the verifier is called with
+    pub fn journal_digest(&self) -> [u8; 32] {
+        Sha256::digest(&self.journal).into()
+    }
+
+    // abi.encode(bytes seal, bytes journal)
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let seal_offset = 64;
+        let journal_offset = seal_offset + 32 + padded_len(self.seal.len());
+
+        let mut encoded = Vec::with_capacity(journal_offset + 32 + padded_len(self.journal.len()));
+        encoded.extend_from_slice(&abi_word(seal_offset as u64));
+        encoded.extend_from_slice(&abi_word(journal_offset as u64));
+        abi_append_bytes(&mut encoded, &self.seal);
+        abi_append_bytes(&mut encoded, &self.journal);
+        encoded
+    }
+
+    // Inverse of abi_encode; rejects anything it would not have produced
+    pub fn abi_decode(encoded: &[u8]) -> Result<Self, String> {
+        let seal_offset = abi_read_usize(encoded, 0)?;
+        let journal_offset = abi_read_usize(encoded, 32)?;
+        if seal_offset != 64 {
+            return Err("Unexpected seal offset".to_string());
+        }
+
+        let seal = abi_read_bytes(encoded, seal_offset)?;
+        if journal_offset != seal_offset + 32 + padded_len(seal.len()) {
+            return Err("Unexpected journal offset".to_string());
+        }
+        let journal = abi_read_bytes(encoded, journal_offset)?;
+        if encoded.len() != journal_offset + 32 + padded_len(journal.len()) {
+            return Err("Trailing bytes after journal".to_string());
+        }
+
+        Ok(Self { seal, journal })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    image_id: String,
+    // Hex of the bincode-serialized receipt
+    receipt: String,
+    // Hex of the journal, also inside `receipt`, for readers without risc0
+    journal: String,
+}
+
+// Self-describing JSON envelope
+pub fn to_json(exported: &ExportedReceipt) -> Result<String, String> {
+    let receipt = bincode::serialize(&exported.receipt).map_err(|e| e.to_string())?;
+    let envelope = Envelope {
+        format: ENVELOPE_FORMAT.to_string(),
+        version: ENVELOPE_VERSION,
+        image_id: hex::encode(exported.image_id.as_bytes()),
+        receipt: hex::encode(receipt),
+        journal: hex::encode(&exported.receipt.journal.bytes),
+    };
+    serde_json::to_string(&envelope).map_err(|e| e.to_string())
+}
+
+pub fn from_json(json: &str) -> Result<ExportedReceipt, String> {
+    let envelope: Envelope = serde_json::from_str(json).map_err(|e| format!("Invalid envelope: {}", e))?;
+    if envelope.format != ENVELOPE_FORMAT {
+        return Err(format!("Unknown envelope format {}", envelope.format));
+    }
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(format!("Unsupported envelope version {}", envelope.version));
+    }
+
+    let image_id = hex::decode(&envelope.image_id).map_err(|e| format!("image_id: {}", e))?;
+    let image_id = Digest::try_from(image_id.as_slice()).map_err(|_| "image_id: expected 32 bytes".to_string())?;
+    let receipt = hex::decode(&envelope.receipt).map_err(|e| format!("receipt: {}", e))?;
+    let receipt: Receipt = bincode::deserialize(&receipt).map_err(|e| format!("Invalid receipt: {}", e))?;
+    let journal = hex::decode(&envelope.journal).map_err(|e| format!("journal: {}", e))?;
+    if journal != receipt.journal.bytes {
+        return Err("Envelope journal does not match the receipt".to_string());
+    }
+
+    Ok(ExportedReceipt { image_id, receipt })
+}
+
+// Binary archival format
+pub fn to_archive(exported: &ExportedReceipt) -> Result<Vec<u8>, String> {
+    let receipt = bincode::serialize(&exported.receipt).map_err(|e| e.to_string())?;
+    let receipt_len = u32::try_from(receipt.len()).map_err(|_| "Receipt too large to archive".to_string())?;
+
+    let mut archive = Vec::with_capacity(4 + 1 + 32 + 4 + receipt.len() + 32);
+    archive.extend_from_slice(&ARCHIVE_MAGIC);
+    archive.push(ARCHIVE_VERSION);
+    archive.extend_from_slice(exported.image_id.as_bytes());
+    archive.extend_from_slice(&receipt_len.to_be_bytes());
+    archive.extend_from_slice(&receipt);
+    let checksum = Sha256::digest(&archive);
+    archive.extend_from_slice(&checksum);
+    Ok(archive)
+}
+
+pub fn from_archive(archive: &[u8]) -> Result<ExportedReceipt, String> {
+    const HEADER_LEN: usize = 4 + 1 + 32 + 4;
+    if archive.len() < HEADER_LEN + 32 {
+        return Err("Archive is truncated".to_string());
+    }
+    let (body, checksum) = archive.split_at(archive.len() - 32);
+    if Sha256::digest(body)[..] != *checksum {
+        return Err("Archive checksum does not match".to_string());
+    }
+    if body[..4] != ARCHIVE_MAGIC {
+        return Err("Not a receipt archive".to_string());
+    }
+    if body[4] != ARCHIVE_VERSION {
+        return Err(format!("Unsupported archive version {}", body[4]));
+    }
+
+    let image_id = Digest::try_from(&body[5..37]).map_err(|_| "Invalid image ID".to_string())?;
+    let receipt_len = u32::from_be_bytes(body[37..41].try_into().unwrap()) as usize;
+    if body.len() != HEADER_LEN + receipt_len {
+        return Err("Archive length does not match its receipt".to_string());
+    }
+    let receipt: Receipt =
+        bincode::deserialize(&body[HEADER_LEN..]).map_err(|e| format!("Invalid receipt: {}", e))?;
+
+    Ok(ExportedReceipt { image_id, receipt })
+}
+
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(32) * 32
+}
+
+fn abi_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn abi_append_bytes(encoded: &mut Vec<u8>, bytes: &[u8]) {
+    encoded.extend_from_slice(&abi_word(bytes.len() as u64));
+    encoded.extend_from_slice(bytes);
+    encoded.resize(encoded.len() + padded_len(bytes.len()) - bytes.len(), 0);
+}
+
+// A uint256 word that must fit in a usize
+fn abi_read_usize(encoded: &[u8], at: usize) -> Result<usize, String> {
+    let end = at.checked_add(32).ok_or("ABI offset out of range")?;
+    let word = encoded.get(at..end).ok_or("ABI data is truncated")?;
+    if word[..24].iter().any(|&b| b != 0) {
+        return Err("ABI word out of range".to_string());
+    }
+    usize::try_from(u64::from_be_bytes(word[24..].try_into().unwrap())).map_err(|e| e.to_string())
+}
+
+// Length-prefixed bytes at `at`, with zero padding
+fn abi_read_bytes(encoded: &[u8], at: usize) -> Result<Vec<u8>, String> {
+    // Bound the length by the data before padding it, so a hostile length
+    // word cannot overflow
+    let len = abi_read_usize(encoded, at)?;
+    if len > encoded.len() {
+        return Err("ABI data is truncated".to_string());
+    }
+    let padded_len = len.div_ceil(32).checked_mul(32).ok_or("ABI length out of range")?;
+    let start = at.checked_add(32).ok_or("ABI offset out of range")?;
+    let end = start.checked_add(padded_len).ok_or("ABI length out of range")?;
+    let padded = encoded.get(start..end).ok_or("ABI data is truncated")?;
+    if padded[len..].iter().any(|&b| b != 0) {
+        return Err("Non-zero ABI padding".to_string());
+    }
+    Ok(padded[..len].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{FakeReceipt, ReceiptClaim};
+
+    #[test]
+    fn abi_round_trip() {
+        let proof = SolidityProof {
+            seal: vec![0xab; 260],
+            journal: vec![1, 2, 3],
+        };
+        let encoded = proof.abi_encode();
+        // Two offsets, then each length word and its padded data
+        assert_eq!(encoded.len(), 64 + 32 + 288 + 32 + 32);
+        assert_eq!(encoded[31], 64);
+        assert_eq!(SolidityProof::abi_decode(&encoded).unwrap(), proof);
+
+        assert!(SolidityProof::abi_decode(&encoded[..encoded.len() - 1]).is_err());
+        let mut padded = encoded.clone();
+        *padded.last_mut().unwrap() = 1;
+        assert!(SolidityProof::abi_decode(&padded).is_err());
+    }
+
+    #[test]
+    fn abi_empty_journal() {
+        let proof = SolidityProof { seal: vec![7; 4], journal: Vec::new() };
+        assert_eq!(SolidityProof::abi_decode(&proof.abi_encode()).unwrap(), proof);
+    }
+
+    #[test]
+    fn abi_rejects_huge_lengths() {
+        let proof = SolidityProof { seal: vec![7; 4], journal: vec![1] };
+        let mut encoded = proof.abi_encode();
+        // Largest length word that still fits a usize
+        encoded[64..96].copy_from_slice(&abi_word(usize::MAX as u64));
+        assert!(SolidityProof::abi_decode(&encoded).is_err());
+        encoded[64..96].copy_from_slice(&abi_word(usize::MAX as u64 - 31));
+        assert!(SolidityProof::abi_decode(&encoded).is_err());
+    }
+
+    fn exported(journal: &[u8]) -> ExportedReceipt {
+        let image_id = Digest::from([7u32; 8]);
+        let claim = ReceiptClaim::ok(image_id, journal.to_vec());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.to_vec());
+        ExportedReceipt::new(image_id, receipt)
+    }
+
+    fn assert_same(a: &ExportedReceipt, b: &ExportedReceipt) {
+        assert_eq!(a.image_id, b.image_id);
+        assert_eq!(bincode::serialize(&a.receipt).unwrap(), bincode::serialize(&b.receipt).unwrap());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let original = exported(b"spend journal");
+        let json = to_json(&original).unwrap();
+        assert_same(&from_json(&json).unwrap(), &original);
+
+        let mut envelope: serde_json::Value = serde_json::from_str(&json).unwrap();
+        envelope["journal"] = hex::encode(b"other journal").into();
+        assert!(from_json(&envelope.to_string()).is_err());
+
+        let mut envelope: serde_json::Value = serde_json::from_str(&json).unwrap();
+        envelope["version"] = (ENVELOPE_VERSION + 1).into();
+        assert!(from_json(&envelope.to_string()).is_err());
+
+        let mut envelope: serde_json::Value = serde_json::from_str(&json).unwrap();
+        envelope["format"] = "other".into();
+        assert!(from_json(&envelope.to_string()).is_err());
+
+        let mut envelope: serde_json::Value = serde_json::from_str(&json).unwrap();
+        envelope["image_id"] = hex::encode([7u8; 31]).into();
+        assert!(from_json(&envelope.to_string()).is_err());
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let original = exported(b"spend journal");
+        let archive = to_archive(&original).unwrap();
+        assert_same(&from_archive(&archive).unwrap(), &original);
+
+        // Any flipped byte breaks the checksum
+        for at in [0, 4, 5, 40, 41, archive.len() / 2, archive.len() - 1] {
+            let mut tampered = archive.clone();
+            tampered[at] ^= 1;
+            assert!(from_archive(&tampered).is_err(), "byte {}", at);
+        }
+        assert!(from_archive(&archive[..archive.len() - 1]).is_err());
+        assert!(from_archive(&archive[..40]).is_err());
+
+        // A wrong version or length is caught even under a valid checksum
+        let rechecksum = |mut body: Vec<u8>| {
+            let checksum = Sha256::digest(&body);
+            body.extend_from_slice(&checksum);
+            body
+        };
+        let body = archive[..archive.len() - 32].to_vec();
+        let mut version = body.clone();
+        version[4] = ARCHIVE_VERSION + 1;
+        assert!(from_archive(&rechecksum(version)).is_err());
+        let mut length = body.clone();
+        length[40] ^= 1;
+        assert!(from_archive(&rechecksum(length)).is_err());
+    }
+}
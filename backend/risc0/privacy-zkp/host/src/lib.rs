@@ -1,77 +1,387 @@
-use privacy_zkp_methods::{GUEST_ELF, GUEST_ID};
+use privacy_zkp_methods::{FACE_MATCH_ELF, GUEST_ELF, SPEND_BATCH_ELF};
 use risc0_zkvm::{
-    ExecutorEnv, Prover, Receipt, 
-    serde::{from_slice, to_vec}
+    default_executor, default_prover, ExecutorEnv, ExitCode, InnerReceipt, Journal, ProverOpts, Receipt,
 };
-use sha2::{Digest, Sha256};
-use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
+use curve25519_dalek::scalar::Scalar;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::sync::Arc;
 
-// Re-export guest types for convenience
-pub use privacy_zkp_methods::guest::{
+pub mod cache;
+pub mod export;
+pub mod service;
+
+use cache::{CacheKey, ReceiptCache};
+use export::ExportedReceipt;
+
+// Re-export the shared spend types for convenience
+pub use privacy_zkp_core::{
     SpendVerificationInput, SpendNoteInput, 
-    MerkleProof, NullifierData, AmountCommitment
+    MerkleProof, NullifierData, AmountCommitment, SpendAuthorization, SpendDomain,
+    AmountMode, AmountDisclosure, BatchNote, BatchSpendInput, VerificationStatus, Note,
+    JournalHeader, SpendJournal, BatchJournal, JOURNAL_VERSION
 };
+use privacy_zkp_methods::guest::FaceMatchInput;
+use privacy_zkp_methods::guest::face::{embedding_commitment, is_valid_embedding_pair, quantize_embedding};
+// Image IDs to verify exported receipts against
+pub use privacy_zkp_methods::{FACE_MATCH_ID, GUEST_ID, SPEND_BATCH_ID};
+// Build trees and paths with the same hashing the guest checks them with
+pub use privacy_zkp_core::{MerkleScheme, MerkleTree};
+// Leaf, nullifier and commitment derivation, for building trees and tracking spent notes
+pub use privacy_zkp_core::{amount_commitment, compute_leaf_hash, derive_nullifier, spending_key_hash};
+// Callers sign eth_signed_message_hash(spend_message_hash(..)) with the note owner's wallet
+pub use privacy_zkp_core::auth::{spend_message_hash, eth_signed_message_hash};
 
 #[derive(Debug)]
 pub struct SpendProof {
     pub receipt: Receipt,
     pub merkle_root: [u8; 32],
     pub nullifier: [u8; 32],
-    pub amount: u64,
+    // None in hidden mode, where the amount is not public
+    pub amount: Option<u64>,
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,
+    pub domain: SpendDomain,
+    pub disclosure: AmountDisclosure,
+    // Openings of the output commitments in hidden mode, for the recipients
+    pub outputs: Vec<AmountCommitment>,
+}
+
+impl SpendProof {
+    // Rebuild a proof from a receipt alone, taking every field from its journal
+    pub fn from_receipt(receipt: Receipt) -> Result<Self, SpendError> {
+        let journal = decode_spend_journal(&receipt)?;
+
+        Ok(SpendProof {
+            receipt,
+            merkle_root: journal.merkle_root,
+            nullifier: journal.nullifier,
+            amount: revealed_amount(&journal.disclosure),
+            recipient: journal.recipient,
+            relayer_fee: journal.relayer_fee,
+            domain: journal.domain,
+            disclosure: journal.disclosure,
+            outputs: Vec::new(),
+        })
+    }
+
+    // The receipt with the spend guest's image ID, for the export encodings
+    pub fn export(&self) -> ExportedReceipt {
+        ExportedReceipt::new(GUEST_ID, self.receipt.clone())
+    }
+
+    pub fn receipt_kind(&self) -> Result<ReceiptKind, String> {
+        ReceiptKind::of(&self.receipt)
+    }
+}
+
+// The amount a journal makes public, the same however the proof was obtained
+fn revealed_amount(disclosure: &AmountDisclosure) -> Option<u64> {
+    match disclosure {
+        AmountDisclosure::Revealed(amount) => Some(*amount),
+        AmountDisclosure::Hidden { .. } => None,
+    }
+}
+
+// Where the spent funds go; bound into the proof so a receipt can't be
+// resubmitted with a different recipient
+#[derive(Debug, Clone)]
+pub struct SpendIntent {
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,
+    // Keep the amount out of the journal, splitting it into these outputs
+    pub hidden: Option<HiddenAmount>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HiddenAmount {
+    pub outputs: Vec<u64>,
+}
+
+// One note of a batch spend, authorized like a single spend of the note
+#[derive(Debug)]
+pub struct BatchNoteRequest {
+    pub note: Note,
+    pub authorization: SpendAuthorization,
+    pub merkle_proof: MerkleProof,
+}
+
+// Several notes spent under one root in a single receipt
+#[derive(Debug)]
+pub struct BatchSpendProof {
+    pub receipt: Receipt,
+    pub merkle_root: [u8; 32],
+    pub nullifiers: Vec<[u8; 32]>,
+    pub total_amount: u64,
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,
+    pub domain: SpendDomain,
+}
+
+impl BatchSpendProof {
+    // The receipt with the batch guest's image ID, for the export encodings
+    pub fn export(&self) -> ExportedReceipt {
+        ExportedReceipt::new(SPEND_BATCH_ID, self.receipt.clone())
+    }
+
+    pub fn receipt_kind(&self) -> Result<ReceiptKind, String> {
+        ReceiptKind::of(&self.receipt)
+    }
+}
+
+// A fresh embedding compared with the registered one; only the reference
+// commitment, threshold and outcome are public
+#[derive(Debug)]
+pub struct FaceMatchProof {
+    pub receipt: Receipt,
+    pub reference_commitment: [u8; 32],
+    pub threshold_bps: u16,
+    pub matched: bool,
+}
+
+// Commitment to store at registration in place of a hash of the raw embedding
+pub fn face_reference_commitment(reference: &[f32], salt: &[u8; 32]) -> [u8; 32] {
+    embedding_commitment(&quantize_embedding(reference), salt)
+}
+
+// Kind of receipt a proof carries. Composite receipts grow with the number of
+// segments; succinct ones are a fixed size and verify the same way. Groth16
+// receipts are the ones a Solidity verifier accepts, and fake receipts come
+// from RISC0_DEV_MODE and only verify in dev mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Composite,
+    Succinct,
+    Groth16,
+    Fake,
+}
+
+impl ReceiptKind {
+    pub fn of(receipt: &Receipt) -> Result<Self, String> {
+        match &receipt.inner {
+            InnerReceipt::Composite(_) => Ok(ReceiptKind::Composite),
+            InnerReceipt::Succinct(_) => Ok(ReceiptKind::Succinct),
+            InnerReceipt::Groth16(_) => Ok(ReceiptKind::Groth16),
+            InnerReceipt::Fake(_) => Ok(ReceiptKind::Fake),
+            _ => Err("Unsupported receipt kind".to_string()),
+        }
+    }
+}
+
+// Outcome of a verification and the kind of receipt that was checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub valid: bool,
+    pub receipt_kind: ReceiptKind,
+}
+
+// What running a guest in the executor, without proving, found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreflightReport {
+    pub cycles: u64,
+    pub segments: usize,
+    // Whether the guest halted normally and committed a journal
+    pub journal_produced: bool,
+    // Status the journal opens with, if one was produced
+    pub status: Option<VerificationStatus>,
+}
+
+impl PreflightReport {
+    // Whether proving the same input would give an accepted spend
+    pub fn would_prove(&self) -> bool {
+        self.journal_produced && self.status == Some(VerificationStatus::Valid)
+    }
+}
+
+// Why a spend could not be proven
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpendError {
+    // The guest ran and rejected the spend for this reason
+    Rejected(VerificationStatus),
+    // The request was malformed or the prover failed
+    Prover(String),
+}
+
+impl fmt::Display for SpendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendError::Rejected(status) => write!(f, "Spend rejected by guest: {:?}", status),
+            SpendError::Prover(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SpendError {}
+
+impl From<String> for SpendError {
+    fn from(message: String) -> Self {
+        SpendError::Prover(message)
+    }
+}
+
+impl From<&str> for SpendError {
+    fn from(message: &str) -> Self {
+        SpendError::Prover(message.to_string())
+    }
+}
+
+// Header of a spend or batch journal. Journals from before versioning have
+// none and are refused; see JOURNAL_VERSION.
+pub fn journal_header(receipt: &Receipt) -> Result<JournalHeader, String> {
+    decode_header(&receipt.journal)
+}
+
+fn decode_header(journal: &Journal) -> Result<JournalHeader, String> {
+    let first_word: u32 = journal.decode().map_err(|e| e.to_string())?;
+    if !JournalHeader::is_versioned(first_word) {
+        return Err("Journal has no header; receipts from before versioned journals are not supported".to_string());
+    }
+    journal.decode().map_err(|e| e.to_string())
+}
+
+// Status the guest committed; rejected spends commit nothing after it
+pub fn journal_status(receipt: &Receipt) -> Result<VerificationStatus, String> {
+    Ok(journal_header(receipt)?.status)
 }
 
+// Public outputs of an accepted spend, from any supported journal version
+pub fn decode_spend_journal(receipt: &Receipt) -> Result<SpendJournal, SpendError> {
+    decode_journal(receipt)
+}
+
+// Public outputs of an accepted batch spend, from any supported journal version
+pub fn decode_batch_journal(receipt: &Receipt) -> Result<BatchJournal, SpendError> {
+    decode_journal(receipt)
+}
+
+// Decode the struct following the journal's header
+fn decode_journal<T: DeserializeOwned>(receipt: &Receipt) -> Result<T, SpendError> {
+    let header = require_valid(receipt)?;
+    let journal = match header.version {
+        JOURNAL_VERSION => receipt
+            .journal
+            .decode::<(JournalHeader, T)>()
+            .map(|(_, journal)| journal),
+        version => return Err(format!("Unsupported journal version {}", version).into()),
+    };
+    journal.map_err(|e| SpendError::Prover(e.to_string()))
+}
+
+// Turn a receipt into an error unless the guest accepted the spend
+fn require_valid(receipt: &Receipt) -> Result<JournalHeader, SpendError> {
+    let header = journal_header(receipt)?;
+    match header.status {
+        VerificationStatus::Valid => Ok(header),
+        status => Err(SpendError::Rejected(status)),
+    }
+}
+
+// Proofs are generated for a single FaceVerifier deployment
 pub struct ProofGenerator {
-    prover: Prover,
+    domain: SpendDomain,
+    compress: bool,
+    cache: Option<Arc<ReceiptCache>>,
 }
 
 impl ProofGenerator {
-    pub fn new() -> Self {
-        Self {
-            prover: Prover::new(GUEST_ELF, GUEST_ID).unwrap()
-        }
+    pub fn new(domain: SpendDomain) -> Self {
+        Self { domain, compress: false, cache: None }
+    }
+
+    // Compress every receipt to a succinct one before returning it. Costs
+    // extra proving time on CPU, but the receipt no longer grows with the
+    // guest's cycle count.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
     }
 
-    // Generate a proof for spending a note
+    // Reuse receipts proven for identical inputs, and store new ones, in `cache`
+    pub fn with_cache(mut self, cache: Arc<ReceiptCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Generate a proof for spending `note`, with the secrets its owner kept
+    // from deposit time so it matches the leaf in the tree. The owner
+    // authorizes the spend by signing spend_message_hash(wallet_address,
+    // nullifier, merkle_root, amount, recipient, relayer_fee, domain), where
+    // the nullifier is the note's (see `Note::to_spend_note`); their private
+    // key never reaches the prover.
     pub fn prove_spend(
         &self,
-        wallet_address: [u8; 20],
-        amount: u64,
+        note: &Note,
+        authorization: SpendAuthorization,
+        intent: SpendIntent,
         merkle_proof: MerkleProof,
         merkle_root: [u8; 32],
-    ) -> Result<SpendProof, String> {
-        // Generate nullifier data
-        let nullifier_data = NullifierData {
-            salt: rand::random(),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        };
+    ) -> Result<SpendProof, SpendError> {
+        let (input, disclosure, outputs) =
+            self.spend_input(note, authorization, &intent, merkle_proof, merkle_root)?;
+        let nullifier = input.spend_note.nullifier;
 
-        // Generate amount commitment
-        let blinding_factor = Scalar::random(&mut rand::thread_rng());
-        let amount_commitment = AmountCommitment {
-            amount,
-            blinding_factor,
-            commitment: privacy_zkp_methods::guest::pedersen::PedersenCommitment::commit(
-                amount,
-                &blinding_factor,
-            ),
-        };
-
-        // Generate nullifier
-        let mut hasher = Sha256::new();
-        hasher.update(&wallet_address);
-        hasher.update(&nullifier_data.salt);
-        hasher.update(&nullifier_data.timestamp.to_be_bytes());
-        let nullifier = hasher.finalize().into();
+        // Generate the proof
+        let receipt = self.prove(GUEST_ELF, GUEST_ID, &input)?;
+        require_valid(&receipt)?;
 
-        // Create spend note
-        let spend_note = SpendNoteInput {
-            wallet_address,
+        Ok(SpendProof {
+            receipt,
+            merkle_root,
             nullifier,
-            amount_commitment,
-            nullifier_data,
+            amount: revealed_amount(&disclosure),
+            recipient: intent.recipient,
+            relayer_fee: intent.relayer_fee,
+            domain: self.domain,
+            disclosure,
+            outputs,
+        })
+    }
+
+    // Run the spend guest on the same input prove_spend would, without
+    // proving, to get its cycle count and catch a rejected spend in seconds
+    pub fn preflight(
+        &self,
+        note: &Note,
+        authorization: SpendAuthorization,
+        intent: SpendIntent,
+        merkle_proof: MerkleProof,
+        merkle_root: [u8; 32],
+    ) -> Result<PreflightReport, String> {
+        let (input, _, _) = self.spend_input(note, authorization, &intent, merkle_proof, merkle_root)?;
+        execute(GUEST_ELF, &input)
+    }
+
+    // The guest input for a spend, with what its journal will disclose and the
+    // openings of any hidden-mode outputs
+    fn spend_input(
+        &self,
+        note: &Note,
+        authorization: SpendAuthorization,
+        intent: &SpendIntent,
+        merkle_proof: MerkleProof,
+        merkle_root: [u8; 32],
+    ) -> Result<(SpendVerificationInput, AmountDisclosure, Vec<AmountCommitment>), String> {
+        let spend_note = self.spend_note(note)?;
+
+        // Commit to each output with a fresh blinding factor in hidden mode
+        let (amount_mode, outputs) = match &intent.hidden {
+            None => (AmountMode::Reveal { expected_amount: note.amount }, Vec::new()),
+            Some(hidden) => {
+                let outputs: Vec<AmountCommitment> = hidden
+                    .outputs
+                    .iter()
+                    .map(|&value| random_commitment(value))
+                    .collect();
+                let mode = AmountMode::Hidden { outputs: outputs.clone() };
+                (mode, outputs)
+            }
+        };
+        let disclosure = match &amount_mode {
+            AmountMode::Reveal { expected_amount } => AmountDisclosure::Revealed(*expected_amount),
+            AmountMode::Hidden { .. } => AmountDisclosure::Hidden {
+                note_commitment: spend_note.amount_commitment.commitment,
+                outputs: outputs.iter().map(|o| o.commitment).collect(),
+            },
         };
 
         // Create verification input
@@ -79,53 +389,356 @@ impl ProofGenerator {
             spend_note,
             merkle_proof,
             merkle_root,
-            expected_amount: amount,
+            amount_mode,
+            authorization,
+            recipient: intent.recipient,
+            relayer_fee: intent.relayer_fee,
+            domain: self.domain,
         };
+        Ok((input, disclosure, outputs))
+    }
+
+    // Generate one proof spending every note in `notes` to the same recipient.
+    // All paths must lead to `merkle_root`, and the journal carries each
+    // note's nullifier and the total amount.
+    pub fn prove_spend_batch(
+        &self,
+        notes: Vec<BatchNoteRequest>,
+        intent: SpendIntent,
+        merkle_root: [u8; 32],
+    ) -> Result<BatchSpendProof, SpendError> {
+        if notes.is_empty() {
+            return Err(SpendError::Rejected(VerificationStatus::EmptyBatch));
+        }
+        if intent.hidden.is_some() {
+            return Err("Batch spends reveal their total amount".into());
+        }
 
-        // Create the executor environment
-        let env = ExecutorEnv::builder()
-            .add_input(&to_vec(&input).unwrap())
-            .build()
-            .unwrap();
+        let mut total_amount: u64 = 0;
+        let mut batch = Vec::with_capacity(notes.len());
+        for request in notes {
+            total_amount = total_amount
+                .checked_add(request.note.amount)
+                .ok_or(SpendError::Rejected(VerificationStatus::AmountOverflow))?;
+            batch.push(BatchNote {
+                spend_note: self.spend_note(&request.note)?,
+                merkle_proof: request.merkle_proof,
+                expected_amount: request.note.amount,
+                authorization: request.authorization,
+            });
+        }
+        let nullifiers = batch.iter().map(|n| n.spend_note.nullifier).collect();
 
-        // Generate the proof
-        let receipt = self.prover.prove(env).map_err(|e| e.to_string())?;
+        let input = BatchSpendInput {
+            notes: batch,
+            merkle_root,
+            recipient: intent.recipient,
+            relayer_fee: intent.relayer_fee,
+            domain: self.domain,
+        };
+        let receipt = self.prove(SPEND_BATCH_ELF, SPEND_BATCH_ID, &input)?;
+        require_valid(&receipt)?;
 
-        Ok(SpendProof {
+        Ok(BatchSpendProof {
             receipt,
             merkle_root,
-            nullifier,
-            amount,
+            nullifiers,
+            total_amount,
+            recipient: intent.recipient,
+            relayer_fee: intent.relayer_fee,
+            domain: self.domain,
+        })
+    }
+
+    // Prove whether `probe` is within `threshold_bps` (cosine similarity in basis
+    // points) of the reference registered as face_reference_commitment(reference, salt)
+    pub fn prove_face_match(
+        &self,
+        probe: &[f32],
+        reference: &[f32],
+        reference_salt: [u8; 32],
+        threshold_bps: u16,
+    ) -> Result<FaceMatchProof, String> {
+        let input = FaceMatchInput {
+            probe: quantize_embedding(probe),
+            reference: quantize_embedding(reference),
+            reference_salt,
+            threshold_bps,
+        };
+        if !is_valid_embedding_pair(&input.probe, &input.reference) {
+            return Err("Embeddings must be non-empty and of equal length".to_string());
+        }
+
+        let receipt = self.prove(FACE_MATCH_ELF, FACE_MATCH_ID, &input)?;
+        let (reference_commitment, threshold_bps, matched): ([u8; 32], u16, bool) =
+            receipt.journal.decode().map_err(|e| e.to_string())?;
+
+        Ok(FaceMatchProof {
+            receipt,
+            reference_commitment,
+            threshold_bps,
+            matched,
         })
     }
+
+    // Prove `elf` on `input`, or take the receipt of an earlier identical
+    // proof from the cache
+    fn prove<T: serde::Serialize>(&self, elf: &[u8], image_id: [u32; 8], input: &T) -> Result<Receipt, String> {
+        let Some(cache) = &self.cache else {
+            return prove(elf, input, self.compress);
+        };
+        let key = CacheKey::new(image_id, input, self.compress)?;
+        if let Some(receipt) = cache.get(&key) {
+            return Ok(receipt);
+        }
+
+        let receipt = prove(elf, input, self.compress)?;
+        // The proof is done; failing to cache it shouldn't fail the request
+        let _ = cache.put(&key, &receipt);
+        Ok(receipt)
+    }
+
+    // The guest's view of a stored note; the nullifier is fixed by the
+    // spending key and the note's leaf on this deployment
+    fn spend_note(&self, note: &Note) -> Result<SpendNoteInput, String> {
+        note.to_spend_note(&self.domain)
+            .ok_or_else(|| "Note blinding factor is not a canonical scalar".to_string())
+    }
+}
+
+// Create a note for `wallet_address` with a fresh spending key and blinding
+// factor, for a deposit. The owner must keep it to spend the note later.
+pub fn new_note(wallet_address: [u8; 20], amount: u64) -> Note {
+    Note {
+        wallet_address,
+        amount,
+        nullifier_data: NullifierData {
+            spending_key: rand::random(),
+        },
+        blinding_factor: Scalar::random(&mut rand::thread_rng()).to_bytes(),
+    }
+}
+
+// Commit to `amount` with a fresh blinding factor
+fn random_commitment(amount: u64) -> AmountCommitment {
+    let blinding_factor = Scalar::random(&mut rand::thread_rng()).to_bytes();
+    amount_commitment(amount, blinding_factor).expect("Random scalars are canonical")
+}
+
+// Run `elf` on `input` in the executor only
+fn execute<T: serde::Serialize>(elf: &[u8], input: &T) -> Result<PreflightReport, String> {
+    let env = ExecutorEnv::builder()
+        .write(input)
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let session = default_executor().execute(env, elf).map_err(|e| e.to_string())?;
+    let journal_produced = matches!(session.exit_code, ExitCode::Halted(0));
+    let status = if journal_produced {
+        Some(decode_header(&session.journal)?.status)
+    } else {
+        None
+    };
+
+    Ok(PreflightReport {
+        cycles: session.cycles(),
+        segments: session.segments.len(),
+        journal_produced,
+        status,
+    })
+}
+
+// Run `elf` on `input` and prove the execution, compressing the receipt to a
+// succinct one if asked
+fn prove<T: serde::Serialize>(elf: &[u8], input: &T, compress: bool) -> Result<Receipt, String> {
+    let env = ExecutorEnv::builder()
+        .write(input)
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let prover = default_prover();
+    let receipt = prover
+        .prove(env, elf)
+        .map(|info| info.receipt)
+        .map_err(|e| e.to_string())?;
+    if !compress {
+        return Ok(receipt);
+    }
+    prover
+        .compress(&ProverOpts::succinct(), &receipt)
+        .map_err(|e| e.to_string())
 }
 
 pub struct ProofVerifier;
 
 impl ProofVerifier {
-    // Verify a spend proof
+    // Verify a spend proof, carrying a composite or succinct receipt
     pub fn verify_spend(proof: &SpendProof) -> Result<bool, String> {
+        Ok(Self::verify_spend_report(proof)?.valid)
+    }
+
+    // Verify a spend proof and report which kind of receipt it carried
+    pub fn verify_spend_report(proof: &SpendProof) -> Result<VerificationReport, String> {
+        let receipt_kind = proof.receipt_kind()?;
+        let valid = Self::check_spend(proof)?;
+        Ok(VerificationReport { valid, receipt_kind })
+    }
+
+    fn check_spend(proof: &SpendProof) -> Result<bool, String> {
         // Verify the RISC Zero proof
         proof.receipt.verify(GUEST_ID).map_err(|e| e.to_string())?;
 
-        // Get the public outputs
-        let journal = proof.receipt.journal.as_ref().ok_or("No journal found")?;
-        
-        // Verify the committed values match what we expect
-        let committed_root: [u8; 32] = from_slice(&journal[0..32]).unwrap();
-        let committed_nullifier: [u8; 32] = from_slice(&journal[32..64]).unwrap();
-        let committed_amount: u64 = from_slice(&journal[64..72]).unwrap();
+        // Get the public outputs the guest committed
+        let journal = match decode_spend_journal(&proof.receipt) {
+            Ok(journal) => journal,
+            Err(SpendError::Rejected(_)) => return Ok(false),
+            Err(SpendError::Prover(e)) => return Err(e),
+        };
 
-        if committed_root != proof.merkle_root {
+        if journal.merkle_root != proof.merkle_root {
+            return Ok(false);
+        }
+        if journal.nullifier != proof.nullifier {
+            return Ok(false);
+        }
+        if journal.disclosure != proof.disclosure {
             return Ok(false);
         }
-        if committed_nullifier != proof.nullifier {
+        if journal.recipient != proof.recipient || journal.relayer_fee != proof.relayer_fee {
             return Ok(false);
         }
-        if committed_amount != proof.amount {
+        if journal.domain != proof.domain {
             return Ok(false);
         }
 
         Ok(true)
     }
-} 
\ No newline at end of file
+
+    // Verify a spend proof and that it pays the recipient and fee of the
+    // transaction about to be submitted to `domain`
+    pub fn verify_spend_for(
+        proof: &SpendProof,
+        intent: &SpendIntent,
+        domain: &SpendDomain,
+    ) -> Result<bool, String> {
+        if proof.recipient != intent.recipient || proof.relayer_fee != intent.relayer_fee {
+            return Ok(false);
+        }
+        if proof.domain != *domain {
+            return Ok(false);
+        }
+        Self::verify_spend(proof)
+    }
+
+    // Verify a batch spend proof against the nullifiers and total it claims
+    pub fn verify_spend_batch(proof: &BatchSpendProof) -> Result<bool, String> {
+        Ok(Self::verify_spend_batch_report(proof)?.valid)
+    }
+
+    // Verify a batch spend proof and report which kind of receipt it carried
+    pub fn verify_spend_batch_report(proof: &BatchSpendProof) -> Result<VerificationReport, String> {
+        let receipt_kind = proof.receipt_kind()?;
+        let valid = Self::check_spend_batch(proof)?;
+        Ok(VerificationReport { valid, receipt_kind })
+    }
+
+    fn check_spend_batch(proof: &BatchSpendProof) -> Result<bool, String> {
+        proof.receipt.verify(SPEND_BATCH_ID).map_err(|e| e.to_string())?;
+
+        let journal = match decode_batch_journal(&proof.receipt) {
+            Ok(journal) => journal,
+            Err(SpendError::Rejected(_)) => return Ok(false),
+            Err(SpendError::Prover(e)) => return Err(e),
+        };
+
+        Ok(journal.merkle_root == proof.merkle_root
+            && journal.nullifiers == proof.nullifiers
+            && journal.total_amount == proof.total_amount
+            && journal.recipient == proof.recipient
+            && journal.relayer_fee == proof.relayer_fee
+            && journal.domain == proof.domain)
+    }
+
+    // Verify a batch spend proof for the transaction about to be submitted to `domain`
+    pub fn verify_spend_batch_for(
+        proof: &BatchSpendProof,
+        intent: &SpendIntent,
+        domain: &SpendDomain,
+    ) -> Result<bool, String> {
+        if proof.recipient != intent.recipient || proof.relayer_fee != intent.relayer_fee {
+            return Ok(false);
+        }
+        if proof.domain != *domain {
+            return Ok(false);
+        }
+        Self::verify_spend_batch(proof)
+    }
+
+    // Verify a face match proof passed for the registered commitment at no less than `threshold_bps`
+    pub fn verify_face_match(
+        proof: &FaceMatchProof,
+        reference_commitment: &[u8; 32],
+        threshold_bps: u16,
+    ) -> Result<bool, String> {
+        proof.receipt.verify(FACE_MATCH_ID).map_err(|e| e.to_string())?;
+
+        let (committed_reference, committed_threshold, committed_match): ([u8; 32], u16, bool) =
+            proof.receipt.journal.decode().map_err(|e| e.to_string())?;
+
+        Ok(committed_reference == *reference_commitment
+            && committed_threshold >= threshold_bps
+            && committed_match
+            && committed_reference == proof.reference_commitment
+            && committed_threshold == proof.threshold_bps
+            && committed_match == proof.matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{FakeReceipt, ReceiptClaim};
+    use serde::Serialize;
+
+    fn receipt_with_journal<T: Serialize>(journal: &T) -> Receipt {
+        let journal: Vec<u8> = risc0_zkvm::serde::to_vec(journal)
+            .unwrap()
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        let claim = ReceiptClaim::ok(GUEST_ID, journal.clone());
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+    }
+
+    #[test]
+    fn versioned_journals_decode() {
+        let journal = SpendJournal {
+            merkle_root: [1; 32],
+            nullifier: [2; 32],
+            disclosure: AmountDisclosure::Revealed(100),
+            recipient: [3; 20],
+            relayer_fee: 1,
+            domain: SpendDomain { chain_id: 11155111, verifier_address: [4; 20] },
+        };
+        let receipt = receipt_with_journal(&(JournalHeader::new(VerificationStatus::Valid), &journal));
+        assert_eq!(journal_header(&receipt).unwrap().version, JOURNAL_VERSION);
+        assert_eq!(decode_spend_journal(&receipt).unwrap(), journal);
+
+        let rejected = receipt_with_journal(&JournalHeader::new(VerificationStatus::InvalidNullifier));
+        assert!(matches!(
+            decode_spend_journal(&rejected),
+            Err(SpendError::Rejected(VerificationStatus::InvalidNullifier))
+        ));
+    }
+
+    #[test]
+    fn unversioned_journals_are_refused() {
+        // What the spend guest committed before journals were versioned
+        let baseline = receipt_with_journal(&([1u8; 32], [2u8; 32], 100u64));
+        let error = journal_header(&baseline).unwrap_err();
+        assert!(error.contains("not supported"), "{}", error);
+        assert!(decode_spend_journal(&baseline).is_err());
+    }
+}
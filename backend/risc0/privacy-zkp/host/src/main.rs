@@ -1,56 +1,458 @@
-// These constants represent the RISC-V ELF and the image ID generated by risc0-build.
-// The ELF is used for proving and the ID is used for verification.
-use methods::{
-    TEST_ELF, TEST_ID
+// Command line prover for the spend guest, driven by the Node backend
+// (RiscZeroProver) and our scripts. Requests and results are JSON; the format
+// is described in this crate's README.md.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use privacy_zkp_host::cache::{CachePolicy, ReceiptCache};
+use privacy_zkp_host::{
+    journal_header, AmountDisclosure, HiddenAmount, MerkleProof, MerkleScheme, Note, NullifierData,
+    PreflightReport, ProofGenerator, ProofVerifier, ReceiptKind, SpendAuthorization, SpendDomain,
+    SpendError, SpendIntent, SpendProof, GUEST_ID,
 };
-use risc0_zkvm::{default_prover, ExecutorEnv};
-
-fn main() {
-    // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
-        .init();
-
-    // An executor environment describes the configurations for the zkVM
-    // including program inputs.
-    // A default ExecutorEnv can be created like so:
-    // `let env = ExecutorEnv::builder().build().unwrap();`
-    // However, this `env` does not have any inputs.
-    //
-    // To add guest input to the executor environment, use
-    // ExecutorEnvBuilder::write().
-    // To access this method, you'll need to use ExecutorEnv::builder(), which
-    // creates an ExecutorEnvBuilder. When you're done adding input, call
-    // ExecutorEnvBuilder::build().
-
-    // For example:
-    let input: u32 = 15 * u32::pow(2, 27) + 1;
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
-
-    // Obtain the default prover.
-    let prover = default_prover();
-
-    // Proof information by proving the specified ELF binary.
-    // This struct contains the receipt along with statistics about execution of the guest
-    let prove_info = prover
-        .prove(env, TEST_ELF)
-        .unwrap();
-
-    // extract the receipt.
-    let receipt = prove_info.receipt;
-
-    // TODO: Implement code for retrieving receipt journal here.
-
-    // For example:
-    let _output: u32 = receipt.journal.decode().unwrap();
-
-    // The receipt was verified at the end of proving, but the below code is an
-    // example of how someone else could verify this receipt.
-    receipt
-        .verify(TEST_ID)
-        .unwrap();
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Parser)]
+#[command(name = "host", about = "Prove and verify IntelliFi note spends")]
+struct Cli {
+    /// Read the request from this file instead of stdin
+    #[arg(long, global = true)]
+    input: Option<PathBuf>,
+    /// Write the result to this file instead of stdout
+    #[arg(long, global = true)]
+    output: Option<PathBuf>,
+    /// Reuse receipts proven for identical requests from this directory, and store new ones there
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prove a spend request, writing the receipt and its journal
+    Prove {
+        /// Compress the receipt to a succinct one before writing it
+        #[arg(long)]
+        succinct: bool,
+    },
+    /// Run a spend request in the executor only, reporting cycles and the journal status
+    Preflight,
+    /// Verify a receipt and the journal values it is expected to carry
+    Verify,
+    /// Decode a receipt's journal without verifying it
+    Inspect,
+}
+
+#[derive(Deserialize)]
+struct ProveRequest {
+    wallet_address: String,
+    authorization: String,
+    amount: String,
+    // The note's secrets from deposit time
+    spending_key: String,
+    blinding_factor: String,
+    merkle_proof: MerkleProofJson,
+    merkle_root: String,
+    recipient: String,
+    #[serde(default)]
+    relayer_fee: Option<String>,
+    chain_id: u64,
+    verifier_address: String,
+    #[serde(default)]
+    hidden: Option<HiddenJson>,
+}
+
+#[derive(Deserialize)]
+struct MerkleProofJson {
+    #[serde(default)]
+    scheme: Option<u8>,
+    path: Vec<String>,
+    #[serde(default)]
+    indices: Vec<bool>,
+}
+
+#[derive(Deserialize)]
+struct HiddenJson {
+    outputs: Vec<String>,
+}
+
+// A receipt plus its journal; `prove` writes it, `verify` and `inspect` read it.
+// Every field but `receipt` is ignored by `inspect` and optional for `verify`,
+// which checks the journal against the ones present.
+#[derive(Serialize, Deserialize, Default)]
+struct SpendJson {
+    receipt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    journal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    journal_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    receipt_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    merkle_root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nullifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amount: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    disclosure: Option<DisclosureJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recipient: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relayer_fee: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chain_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verifier_address: Option<String>,
+    // Openings of the hidden-mode outputs, for their recipients only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<OutputJson>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DisclosureJson {
+    Revealed(String),
+    Hidden { note_commitment: String, outputs: Vec<String> },
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutputJson {
+    amount: String,
+    blinding_factor: String,
+    commitment: String,
+}
+
+// A parsed prove request
+struct SpendRequest {
+    note: Note,
+    authorization: SpendAuthorization,
+    intent: SpendIntent,
+    merkle_proof: MerkleProof,
+    merkle_root: [u8; 32],
+    domain: SpendDomain,
+}
+
+#[derive(Serialize)]
+struct PreflightResult {
+    cycles: u64,
+    segments: usize,
+    journal_produced: bool,
+    status: Option<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyResult {
+    valid: bool,
+    receipt_kind: String,
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, String> {
+    let input = read_input(cli.input.as_ref())?;
+    let (result, code) = match &cli.command {
+        Command::Prove { succinct } => (
+            serde_json::to_value(prove(&input, *succinct, cli.cache_dir.as_ref())?),
+            ExitCode::SUCCESS,
+        ),
+        Command::Preflight => {
+            let report = preflight(&input)?;
+            let code = if report.would_prove() { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+            let result = PreflightResult {
+                cycles: report.cycles,
+                segments: report.segments,
+                journal_produced: report.journal_produced,
+                status: report.status.map(|status| format!("{:?}", status)),
+            };
+            (serde_json::to_value(result), code)
+        }
+        Command::Verify => {
+            let result = verify(&input)?;
+            let code = if result.valid { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+            (serde_json::to_value(result), code)
+        }
+        Command::Inspect => (serde_json::to_value(inspect(&input)?), ExitCode::SUCCESS),
+    };
+    let result = result.map_err(|e| e.to_string())?;
+    write_output(cli.output.as_ref(), &result)?;
+    Ok(code)
+}
+
+fn prove(input: &str, succinct: bool, cache_dir: Option<&PathBuf>) -> Result<SpendJson, String> {
+    let request = parse_spend_request(input)?;
+    let mut generator = ProofGenerator::new(request.domain).with_compression(succinct);
+    if let Some(dir) = cache_dir {
+        generator = generator.with_cache(Arc::new(ReceiptCache::open(dir, CachePolicy::default())?));
+    }
+    let proof = generator
+        .prove_spend(
+            &request.note,
+            request.authorization,
+            request.intent,
+            request.merkle_proof,
+            request.merkle_root,
+        )
+        // Rejections read "Spend rejected by guest: <status>", for callers to match on
+        .map_err(|e| e.to_string())?;
+
+    let mut result = spend_json(&proof)?;
+    result.outputs = proof
+        .outputs
+        .iter()
+        .map(|output| OutputJson {
+            amount: output.amount.to_string(),
+            blinding_factor: hex::encode(output.blinding_factor),
+            commitment: hex::encode(output.commitment),
+        })
+        .collect();
+    Ok(result)
+}
+
+fn preflight(input: &str) -> Result<PreflightReport, String> {
+    let request = parse_spend_request(input)?;
+    ProofGenerator::new(request.domain).preflight(
+        &request.note,
+        request.authorization,
+        request.intent,
+        request.merkle_proof,
+        request.merkle_root,
+    )
+}
+
+fn parse_spend_request(input: &str) -> Result<SpendRequest, String> {
+    let request: ProveRequest =
+        serde_json::from_str(input).map_err(|e| format!("Invalid prove request: {}", e))?;
+
+    let scheme_version = request.merkle_proof.scheme.unwrap_or(MerkleScheme::SortedSha256.version());
+    let merkle_proof = MerkleProof {
+        scheme: MerkleScheme::from_version(scheme_version)
+            .ok_or_else(|| format!("Unknown Merkle scheme {}", scheme_version))?,
+        path: request
+            .merkle_proof
+            .path
+            .iter()
+            .map(|node| parse_hex_array(node, "merkle_proof.path"))
+            .collect::<Result<_, _>>()?,
+        indices: request.merkle_proof.indices,
+    };
+    let hidden = match request.hidden {
+        Some(hidden) => Some(HiddenAmount {
+            outputs: hidden
+                .outputs
+                .iter()
+                .map(|value| parse_u64(value, "hidden.outputs"))
+                .collect::<Result<_, _>>()?,
+        }),
+        None => None,
+    };
+    let intent = SpendIntent {
+        recipient: parse_hex_array(&request.recipient, "recipient")?,
+        relayer_fee: match &request.relayer_fee {
+            Some(fee) => parse_u64(fee, "relayer_fee")?,
+            None => 0,
+        },
+        hidden,
+    };
+    let domain = SpendDomain {
+        chain_id: request.chain_id,
+        verifier_address: parse_hex_array(&request.verifier_address, "verifier_address")?,
+    };
+
+    let note = Note {
+        wallet_address: parse_hex_array(&request.wallet_address, "wallet_address")?,
+        amount: parse_u64(&request.amount, "amount")?,
+        nullifier_data: NullifierData {
+            spending_key: parse_hex_array(&request.spending_key, "spending_key")?,
+        },
+        blinding_factor: parse_hex_array(&request.blinding_factor, "blinding_factor")?,
+    };
+
+    Ok(SpendRequest {
+        note,
+        authorization: SpendAuthorization { signature: parse_hex(&request.authorization, "authorization")? },
+        intent,
+        merkle_proof,
+        merkle_root: parse_hex_array(&request.merkle_root, "merkle_root")?,
+        domain,
+    })
+}
+
+fn verify(input: &str) -> Result<VerifyResult, String> {
+    let expected: SpendJson =
+        serde_json::from_str(input).map_err(|e| format!("Invalid verify request: {}", e))?;
+    let receipt = decode_receipt(&expected.receipt)?;
+    let receipt_kind = receipt_kind_name(ReceiptKind::of(&receipt)?);
+
+    let proof = match SpendProof::from_receipt(receipt) {
+        Ok(proof) => proof,
+        Err(SpendError::Rejected(status)) => {
+            return Ok(VerifyResult {
+                valid: false,
+                receipt_kind,
+                status: Some(format!("{:?}", status)),
+                reason: Some("Spend was rejected by the guest".to_string()),
+            })
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    // Compare the journal with every expectation the caller gave
+    let actual = spend_json(&proof)?;
+    let mismatch = [
+        ("merkle_root", expected.merkle_root.is_some() && expected.merkle_root != actual.merkle_root),
+        ("nullifier", expected.nullifier.is_some() && expected.nullifier != actual.nullifier),
+        ("amount", expected.amount.is_some() && expected.amount != actual.amount),
+        ("disclosure", expected.disclosure.is_some() && expected.disclosure != actual.disclosure),
+        ("recipient", expected.recipient.is_some() && expected.recipient != actual.recipient),
+        ("relayer_fee", expected.relayer_fee.is_some() && expected.relayer_fee != actual.relayer_fee),
+        ("chain_id", expected.chain_id.is_some() && expected.chain_id != actual.chain_id),
+        (
+            "verifier_address",
+            expected.verifier_address.is_some() && expected.verifier_address != actual.verifier_address,
+        ),
+    ]
+    .into_iter()
+    .find(|(_, differs)| *differs);
+    if let Some((field, _)) = mismatch {
+        return Ok(VerifyResult {
+            valid: false,
+            receipt_kind,
+            status: actual.status,
+            reason: Some(format!("Journal {} does not match", field)),
+        });
+    }
+
+    // A seal that does not verify is an invalid proof, not a failure of this command
+    if let Err(e) = proof.receipt.verify(GUEST_ID) {
+        return Ok(VerifyResult {
+            valid: false,
+            receipt_kind,
+            status: actual.status,
+            reason: Some(format!("Seal does not verify: {}", e)),
+        });
+    }
+
+    let valid = ProofVerifier::verify_spend_report(&proof)?.valid;
+    Ok(VerifyResult {
+        valid,
+        receipt_kind,
+        status: actual.status,
+        reason: (!valid).then(|| "Receipt does not verify against the spend guest".to_string()),
+    })
+}
+
+fn inspect(input: &str) -> Result<SpendJson, String> {
+    let request: SpendJson =
+        serde_json::from_str(input).map_err(|e| format!("Invalid inspect request: {}", e))?;
+    let receipt = decode_receipt(&request.receipt)?;
+
+    match SpendProof::from_receipt(receipt.clone()) {
+        Ok(proof) => spend_json(&proof),
+        Err(SpendError::Rejected(status)) => Ok(SpendJson {
+            receipt: request.receipt,
+            journal: Some(hex::encode(&receipt.journal.bytes)),
+            journal_version: Some(journal_header(&receipt)?.version),
+            receipt_kind: Some(receipt_kind_name(ReceiptKind::of(&receipt)?)),
+            status: Some(format!("{:?}", status)),
+            ..Default::default()
+        }),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// The receipt and every public value of a proof
+fn spend_json(proof: &SpendProof) -> Result<SpendJson, String> {
+    let receipt = bincode::serialize(&proof.receipt).map_err(|e| e.to_string())?;
+    let disclosure = match &proof.disclosure {
+        AmountDisclosure::Revealed(amount) => DisclosureJson::Revealed(amount.to_string()),
+        AmountDisclosure::Hidden { note_commitment, outputs } => DisclosureJson::Hidden {
+            note_commitment: hex::encode(note_commitment),
+            outputs: outputs.iter().map(hex::encode).collect(),
+        },
+    };
+    let amount = proof.amount.map(|amount| amount.to_string());
+    let header = journal_header(&proof.receipt)?;
+
+    Ok(SpendJson {
+        receipt: hex::encode(receipt),
+        journal: Some(hex::encode(&proof.receipt.journal.bytes)),
+        journal_version: Some(header.version),
+        receipt_kind: Some(receipt_kind_name(proof.receipt_kind()?)),
+        status: Some(format!("{:?}", header.status)),
+        merkle_root: Some(hex::encode(proof.merkle_root)),
+        nullifier: Some(hex::encode(proof.nullifier)),
+        amount,
+        disclosure: Some(disclosure),
+        recipient: Some(hex::encode(proof.recipient)),
+        relayer_fee: Some(proof.relayer_fee.to_string()),
+        chain_id: Some(proof.domain.chain_id),
+        verifier_address: Some(hex::encode(proof.domain.verifier_address)),
+        outputs: Vec::new(),
+    })
+}
+
+fn receipt_kind_name(kind: ReceiptKind) -> String {
+    format!("{:?}", kind).to_lowercase()
+}
+
+fn decode_receipt(receipt: &str) -> Result<Receipt, String> {
+    bincode::deserialize(&parse_hex(receipt, "receipt")?).map_err(|e| format!("Invalid receipt: {}", e))
+}
+
+fn read_input(path: Option<&PathBuf>) -> Result<String, String> {
+    match path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e)),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+            Ok(input)
+        }
+    }
+}
+
+fn write_output(path: Option<&PathBuf>, value: &serde_json::Value) -> Result<(), String> {
+    let mut json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    json.push('\n');
+    match path {
+        Some(path) => fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e)),
+        None => io::stdout().write_all(json.as_bytes()).map_err(|e| e.to_string()),
+    }
+}
+
+// Hex with or without a 0x prefix
+fn parse_hex(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value)).map_err(|e| format!("{}: {}", field, e))
+}
+
+fn parse_hex_array<const N: usize>(value: &str, field: &str) -> Result<[u8; N], String> {
+    parse_hex(value, field)?
+        .try_into()
+        .map_err(|_| format!("{}: expected {} bytes", field, N))
+}
+
+// Amounts are decimal strings, since JSON numbers lose precision above 2^53 in JavaScript
+fn parse_u64(value: &str, field: &str) -> Result<u64, String> {
+    value.parse().map_err(|e| format!("{}: {}", field, e))
 }
@@ -0,0 +1,49 @@
+// The proving service: ProofGenerator behind the privacy-zkp-jobs queue, so
+// spends are proved on a fixed pool of worker threads instead of on the
+// caller's thread. See that crate for how jobs are scheduled, cancelled and
+// collected.
+pub use privacy_zkp_jobs::{
+    JobError, JobId, JobQueue, JobStatus, Priority, ServiceConfig, DEFAULT_JOB_MEMORY, DEFAULT_RESULT_TTL,
+};
+
+use crate::{
+    BatchNoteRequest, BatchSpendProof, MerkleProof, Note, ProofGenerator, SpendAuthorization,
+    SpendError, SpendIntent, SpendProof,
+};
+
+// What ProofGenerator proves, as a job
+#[derive(Debug)]
+pub enum ProofRequest {
+    Spend {
+        note: Note,
+        authorization: SpendAuthorization,
+        intent: SpendIntent,
+        merkle_proof: MerkleProof,
+        merkle_root: [u8; 32],
+    },
+    SpendBatch {
+        notes: Vec<BatchNoteRequest>,
+        intent: SpendIntent,
+        merkle_root: [u8; 32],
+    },
+}
+
+#[derive(Debug)]
+pub enum ProofOutput {
+    Spend(SpendProof),
+    SpendBatch(BatchSpendProof),
+}
+
+pub type ProofService = JobQueue<ProofRequest, ProofOutput, SpendError>;
+
+// A service proving with `generator` on `config.workers` threads
+pub fn proof_service(config: ServiceConfig, generator: ProofGenerator) -> ProofService {
+    JobQueue::new(config, move |request| match request {
+        ProofRequest::Spend { note, authorization, intent, merkle_proof, merkle_root } => generator
+            .prove_spend(&note, authorization, intent, merkle_proof, merkle_root)
+            .map(ProofOutput::Spend),
+        ProofRequest::SpendBatch { notes, intent, merkle_root } => generator
+            .prove_spend_batch(notes, intent, merkle_root)
+            .map(ProofOutput::SpendBatch),
+    })
+}
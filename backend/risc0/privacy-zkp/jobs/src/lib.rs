@@ -0,0 +1,419 @@
+// A job queue for provers: jobs are queued and run on a fixed pool of worker
+// threads instead of on the caller's thread.
+//
+// - Workers take the highest-priority job first, oldest first within a
+//   priority.
+// - Each job reserves an estimate of the memory its prover needs, and a job
+//   only starts once its reservation fits in the budget next to the running
+//   ones. A job larger than the whole budget runs alone.
+// - A queued job can be cancelled outright. The prover can't be interrupted,
+//   so cancelling a running job lets it finish and discards its proof.
+// - `status` reports where a job is; `wait` blocks until it is done and takes
+//   its result, after which the queue forgets the job. Results nobody takes
+//   are dropped once they are older than `ServiceConfig::result_ttl`.
+//
+// It knows nothing about risc0, so drew-v and privacy-zkp-host share it across
+// their zkVM versions. The host's `service::ProofService` is this queue around
+// its ProofGenerator.
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// Peak memory of proving one segment at the default segment size; the local
+// prover proves segments one after another, so this is per job
+pub const DEFAULT_JOB_MEMORY: u64 = 4 << 30;
+
+// How long a finished job's result waits to be taken
+pub const DEFAULT_RESULT_TTL: Duration = Duration::from_secs(600);
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    // Waiting for a worker, with this many jobs ahead of it
+    Queued { ahead: usize },
+    Running,
+    Succeeded,
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub enum JobError<E> {
+    Cancelled,
+    // Never submitted, or its result was already taken or expired
+    UnknownJob,
+    Failed(E),
+}
+
+impl<E: fmt::Display> fmt::Display for JobError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Cancelled => write!(f, "Proof job was cancelled"),
+            JobError::UnknownJob => write!(f, "Unknown proof job"),
+            JobError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for JobError<E> {}
+
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    // Jobs proving at once
+    pub workers: usize,
+    // Total memory the running jobs may reserve
+    pub memory_budget: u64,
+    // What a job reserves unless submitted with its own estimate
+    pub job_memory: u64,
+    // How long a result is kept for `wait` once its job is done
+    pub result_ttl: Duration,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            memory_budget: DEFAULT_JOB_MEMORY,
+            job_memory: DEFAULT_JOB_MEMORY,
+            result_ttl: DEFAULT_RESULT_TTL,
+        }
+    }
+}
+
+type Runner<R, T, E> = dyn Fn(R) -> Result<T, E> + Send + Sync;
+
+struct Job<R> {
+    id: JobId,
+    priority: Priority,
+    memory: u64,
+    request: R,
+}
+
+impl<R> Job<R> {
+    // Jobs that compare greater are taken first: highest priority, then oldest
+    fn order(&self) -> (Priority, Reverse<JobId>) {
+        (self.priority, Reverse(self.id))
+    }
+}
+
+struct State<R, T, E> {
+    // In submission order
+    queue: Vec<Job<R>>,
+    running: HashSet<JobId>,
+    cancelled: HashSet<JobId>,
+    // Results not yet taken, with when their job finished
+    finished: HashMap<JobId, (Instant, Result<T, JobError<E>>)>,
+    memory_in_use: u64,
+    next_id: JobId,
+    shutdown: bool,
+}
+
+impl<R, T, E> State<R, T, E> {
+    // Index of the job a worker should take next
+    fn next_job(&self) -> Option<usize> {
+        self.queue
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, job)| job.order())
+            .map(|(index, _)| index)
+    }
+
+    fn fits(&self, memory: u64, budget: u64) -> bool {
+        self.running.is_empty() || self.memory_in_use + memory <= budget
+    }
+
+    // Record a result, dropping the ones nobody took within `ttl` so a caller
+    // that never waits can't grow the map without bound
+    fn finish(&mut self, id: JobId, result: Result<T, JobError<E>>, ttl: Duration) {
+        let now = Instant::now();
+        self.finished.retain(|_, (at, _)| now.duration_since(*at) < ttl);
+        self.finished.insert(id, (now, result));
+    }
+}
+
+struct Shared<R, T, E> {
+    state: Mutex<State<R, T, E>>,
+    // Signalled when a job is queued, a job finishes, or the service stops
+    changed: Condvar,
+    config: ServiceConfig,
+    run: Box<Runner<R, T, E>>,
+}
+
+impl<R, T, E> Shared<R, T, E> {
+    fn lock(&self) -> MutexGuard<'_, State<R, T, E>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct JobQueue<R, T, E> {
+    shared: Arc<Shared<R, T, E>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+// A prover that panics fails its job with E::from of the message
+impl<R: Send + 'static, T: Send + 'static, E: From<String> + fmt::Display + Send + 'static> JobQueue<R, T, E> {
+    pub fn new(config: ServiceConfig, run: impl Fn(R) -> Result<T, E> + Send + Sync + 'static) -> Self {
+        let workers = config.workers.max(1);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: Vec::new(),
+                running: HashSet::new(),
+                cancelled: HashSet::new(),
+                finished: HashMap::new(),
+                memory_in_use: 0,
+                next_id: 0,
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+            config,
+            run: Box::new(run),
+        });
+
+        let workers = (0..workers)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || work(&shared))
+            })
+            .collect();
+        Self { shared, workers }
+    }
+
+    pub fn submit(&self, request: R, priority: Priority) -> JobId {
+        self.submit_with_memory(request, priority, self.shared.config.job_memory)
+    }
+
+    // Submit a job expected to need `memory` bytes while it proves
+    pub fn submit_with_memory(&self, request: R, priority: Priority, memory: u64) -> JobId {
+        let mut state = self.shared.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.queue.push(Job { id, priority, memory, request });
+        drop(state);
+        self.shared.changed.notify_all();
+        id
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        let state = self.shared.lock();
+        if let Some(job) = state.queue.iter().find(|job| job.id == id) {
+            let ahead = state.queue.iter().filter(|other| other.order() > job.order()).count();
+            return Some(JobStatus::Queued { ahead });
+        }
+        if state.running.contains(&id) {
+            return Some(JobStatus::Running);
+        }
+        state.finished.get(&id).map(|(_, result)| match result {
+            Ok(_) => JobStatus::Succeeded,
+            Err(JobError::Failed(e)) => JobStatus::Failed(e.to_string()),
+            Err(_) => JobStatus::Cancelled,
+        })
+    }
+
+    // Cancel a queued or running job; false if it already finished or is unknown
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut state = self.shared.lock();
+        if let Some(index) = state.queue.iter().position(|job| job.id == id) {
+            state.queue.remove(index);
+            let ttl = self.shared.config.result_ttl;
+            state.finish(id, Err(JobError::Cancelled), ttl);
+        } else if state.running.contains(&id) {
+            state.cancelled.insert(id);
+        } else {
+            return false;
+        }
+        drop(state);
+        self.shared.changed.notify_all();
+        true
+    }
+
+    // Block until the job is done and take its result
+    pub fn wait(&self, id: JobId) -> Result<T, JobError<E>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some((_, result)) = state.finished.remove(&id) {
+                return result;
+            }
+            let pending = state.running.contains(&id) || state.queue.iter().any(|job| job.id == id);
+            if !pending {
+                return Err(JobError::UnknownJob);
+            }
+            state = self.shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl<R, T, E> Drop for JobQueue<R, T, E> {
+    // Queued jobs are dropped; running ones finish first
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.shutdown = true;
+        state.queue.clear();
+        drop(state);
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work<R, T, E: From<String>>(shared: &Shared<R, T, E>) {
+    loop {
+        let job = {
+            let mut state = shared.lock();
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                // Strictly by priority: a job waiting for memory holds back the
+                // jobs behind it, so a large job isn't starved by small ones
+                if let Some(index) = state.next_job() {
+                    if state.fits(state.queue[index].memory, shared.config.memory_budget) {
+                        let job = state.queue.remove(index);
+                        state.running.insert(job.id);
+                        state.memory_in_use += job.memory;
+                        break job;
+                    }
+                }
+                state = shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| (shared.run)(job.request)))
+            .unwrap_or_else(|_| Err(E::from("Prover panicked".to_string())));
+
+        let mut state = shared.lock();
+        state.running.remove(&job.id);
+        state.memory_in_use -= job.memory;
+        let result = if state.cancelled.remove(&job.id) {
+            Err(JobError::Cancelled)
+        } else {
+            result.map_err(JobError::Failed)
+        };
+        state.finish(job.id, result, shared.config.result_ttl);
+        drop(state);
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // The queue, the jobs in the order they started, and the job release
+    type Gated = (JobQueue<u32, u32, String>, Arc<Mutex<Vec<u32>>>, mpsc::Sender<()>);
+
+    // A queue whose jobs record their start and then block until released
+    fn gated_queue(config: ServiceConfig) -> Gated {
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let (release, gate) = mpsc::channel::<()>();
+        let gate = Mutex::new(gate);
+        let log = Arc::clone(&started);
+        let queue = JobQueue::new(config, move |n: u32| {
+            log.lock().unwrap().push(n);
+            gate.lock().unwrap().recv().unwrap();
+            if n == 0 {
+                return Err("zero".into());
+            }
+            Ok(n * 2)
+        });
+        (queue, started, release)
+    }
+
+    fn wait_until_running(queue: &JobQueue<u32, u32, String>, id: JobId) {
+        while queue.status(id) != Some(JobStatus::Running) {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn jobs_run_by_priority_within_the_memory_budget() {
+        // Two workers, but only one job's worth of memory
+        let config = ServiceConfig {
+            workers: 2,
+            memory_budget: 10,
+            job_memory: 10,
+            ..ServiceConfig::default()
+        };
+        let (queue, started, release) = gated_queue(config);
+
+        let first = queue.submit(1, Priority::Normal);
+        wait_until_running(&queue, first);
+        let low = queue.submit(2, Priority::Low);
+        let high = queue.submit(3, Priority::High);
+        let failing = queue.submit_with_memory(0, Priority::Low, 1);
+        assert_eq!(queue.status(high), Some(JobStatus::Queued { ahead: 0 }));
+        assert_eq!(queue.status(low), Some(JobStatus::Queued { ahead: 1 }));
+
+        // The second worker can't start anything while the first job holds the budget
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(*started.lock().unwrap(), vec![1]);
+
+        for _ in 0..4 {
+            release.send(()).unwrap();
+        }
+        assert_eq!(queue.wait(first).unwrap(), 2);
+        assert_eq!(queue.wait(high).unwrap(), 6);
+        assert_eq!(queue.wait(low).unwrap(), 4);
+        assert!(matches!(queue.wait(failing), Err(JobError::Failed(e)) if e == "zero"));
+        assert_eq!(*started.lock().unwrap(), vec![1, 3, 2, 0]);
+
+        // Results are taken once
+        assert_eq!(queue.status(first), None);
+        assert!(matches!(queue.wait(first), Err(JobError::UnknownJob)));
+    }
+
+    #[test]
+    fn cancelled_jobs_return_no_proof() {
+        let (queue, started, release) = gated_queue(ServiceConfig::default());
+
+        let running = queue.submit(1, Priority::Normal);
+        wait_until_running(&queue, running);
+        let queued = queue.submit(2, Priority::High);
+
+        assert!(queue.cancel(queued));
+        assert!(queue.cancel(running));
+        assert_eq!(queue.status(queued), Some(JobStatus::Cancelled));
+        assert_eq!(queue.status(running), Some(JobStatus::Running));
+
+        release.send(()).unwrap();
+        assert!(matches!(queue.wait(running), Err(JobError::Cancelled)));
+        assert!(matches!(queue.wait(queued), Err(JobError::Cancelled)));
+        assert!(!queue.cancel(running));
+        // The cancelled queued job never started
+        assert_eq!(*started.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn untaken_results_expire() {
+        let config = ServiceConfig { result_ttl: Duration::from_millis(20), ..ServiceConfig::default() };
+        let (queue, _, release) = gated_queue(config);
+
+        // Nobody waits for this one
+        let abandoned = queue.submit(1, Priority::Normal);
+        release.send(()).unwrap();
+        while queue.status(abandoned) != Some(JobStatus::Succeeded) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(30));
+
+        // The next result to come in sweeps it out
+        let next = queue.submit(2, Priority::Normal);
+        release.send(()).unwrap();
+        assert_eq!(queue.wait(next).unwrap(), 4);
+        assert_eq!(queue.status(abandoned), None);
+        assert!(matches!(queue.wait(abandoned), Err(JobError::UnknownJob)));
+    }
+}
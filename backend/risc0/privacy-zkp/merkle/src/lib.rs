@@ -0,0 +1,241 @@
+// Merkle hashing shared by the guest, host, drew-v and (by convention) the
+// TypeScript tree and FaceVerifier contract.
+//
+// Every proof names the scheme it was built with, so a path produced by any of
+// them can be checked by the others:
+//
+//   OrderedSha256   - sha256(left || right), position taken from `indices`
+//   SortedSha256    - sha256(min || max), what merkletreejs does with `sortPairs`
+//   SortedKeccak256 - keccak256(min || max), what FaceVerifier.verifyMerkleProof does
+//
+// Leaves are 32-byte hashes; a level with an odd node count promotes its last
+// node unchanged, as merkletreejs does.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleScheme {
+    OrderedSha256 = 1,
+    SortedSha256 = 2,
+    SortedKeccak256 = 3,
+}
+
+impl MerkleScheme {
+    // Stable identifier, safe to store and send between components
+    pub fn version(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_version(version: u8) -> Option<Self> {
+        match version {
+            1 => Some(Self::OrderedSha256),
+            2 => Some(Self::SortedSha256),
+            3 => Some(Self::SortedKeccak256),
+            _ => None,
+        }
+    }
+
+    // Whether sibling positions come from the proof rather than byte order
+    pub fn uses_indices(self) -> bool {
+        self == Self::OrderedSha256
+    }
+
+    // Hash two children, `left` being the node on the left of the tree
+    pub fn hash_pair(self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        match self {
+            Self::OrderedSha256 => sha256(left, right),
+            Self::SortedSha256 => {
+                let (a, b) = sorted(left, right);
+                sha256(a, b)
+            }
+            Self::SortedKeccak256 => {
+                let (a, b) = sorted(left, right);
+                let mut hasher = Keccak256::new();
+                hasher.update(a);
+                hasher.update(b);
+                hasher.finalize().into()
+            }
+        }
+    }
+}
+
+fn sha256(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(a);
+    hasher.update(b);
+    hasher.finalize().into()
+}
+
+fn sorted<'a>(a: &'a [u8; 32], b: &'a [u8; 32]) -> (&'a [u8; 32], &'a [u8; 32]) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub scheme: MerkleScheme,
+    pub path: Vec<[u8; 32]>,
+    // indices[i] is true when the running node is the left child at level i.
+    // Only read by OrderedSha256, but always filled in by MerkleTree::proof.
+    pub indices: Vec<bool>,
+}
+
+impl MerkleProof {
+    // Fold the path over `leaf`, None if the proof is malformed for its scheme
+    pub fn compute_root(&self, leaf: &[u8; 32]) -> Option<[u8; 32]> {
+        if self.scheme.uses_indices() && self.indices.len() != self.path.len() {
+            return None;
+        }
+
+        let mut current = *leaf;
+        for (i, sibling) in self.path.iter().enumerate() {
+            let is_left = self.indices.get(i).copied().unwrap_or(true);
+            current = if is_left {
+                self.scheme.hash_pair(&current, sibling)
+            } else {
+                self.scheme.hash_pair(sibling, &current)
+            };
+        }
+        Some(current)
+    }
+
+    pub fn verify(&self, leaf: &[u8; 32], root: &[u8; 32]) -> bool {
+        self.compute_root(leaf).as_ref() == Some(root)
+    }
+}
+
+// An in-memory tree, for building proofs off-chain
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    scheme: MerkleScheme,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(scheme: MerkleScheme, leaves: Vec<[u8; 32]>) -> Self {
+        let mut levels = Vec::new();
+        let mut current = leaves;
+        while current.len() > 1 {
+            let next = current
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => scheme.hash_pair(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(current);
+            current = next;
+        }
+        levels.push(current);
+        Self { scheme, levels }
+    }
+
+    pub fn scheme(&self) -> MerkleScheme {
+        self.scheme
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.levels[0]
+    }
+
+    // Root of the tree, all zeroes when it has no leaves
+    pub fn root(&self) -> [u8; 32] {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or([0u8; 32])
+    }
+
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaves().len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut indices = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            // A promoted node has no sibling and adds no step
+            if let Some(node) = level.get(sibling) {
+                path.push(*node);
+                indices.push(position & 1 == 0);
+            }
+            position /= 2;
+        }
+
+        Some(MerkleProof {
+            scheme: self.scheme,
+            path,
+            indices,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMES: [MerkleScheme; 3] = [
+        MerkleScheme::OrderedSha256,
+        MerkleScheme::SortedSha256,
+        MerkleScheme::SortedKeccak256,
+    ];
+
+    fn leaves(n: u8) -> Vec<[u8; 32]> {
+        (0..n).map(|i| [i.wrapping_mul(37).wrapping_add(11); 32]).collect()
+    }
+
+    #[test]
+    fn proofs_verify_for_every_leaf_and_scheme() {
+        for scheme in SCHEMES {
+            for n in 1..=9 {
+                let tree = MerkleTree::new(scheme, leaves(n));
+                for (i, leaf) in tree.leaves().iter().enumerate() {
+                    let proof = tree.proof(i).unwrap();
+                    assert!(proof.verify(leaf, &tree.root()), "{:?} n={} i={}", scheme, n, i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sorted_schemes_ignore_indices() {
+        let tree = MerkleTree::new(MerkleScheme::SortedKeccak256, leaves(5));
+        let mut proof = tree.proof(3).unwrap();
+        proof.indices.clear();
+        assert!(proof.verify(&tree.leaves()[3], &tree.root()));
+    }
+
+    #[test]
+    fn ordered_scheme_rejects_missing_indices() {
+        let tree = MerkleTree::new(MerkleScheme::OrderedSha256, leaves(4));
+        let mut proof = tree.proof(1).unwrap();
+        proof.indices.pop();
+        assert_eq!(proof.compute_root(&tree.leaves()[1]), None);
+    }
+
+    #[test]
+    fn schemes_are_distinct_and_versioned() {
+        let a = MerkleTree::new(MerkleScheme::SortedSha256, leaves(4)).root();
+        let b = MerkleTree::new(MerkleScheme::SortedKeccak256, leaves(4)).root();
+        assert_ne!(a, b);
+        for scheme in SCHEMES {
+            assert_eq!(MerkleScheme::from_version(scheme.version()), Some(scheme));
+        }
+        assert_eq!(MerkleScheme::from_version(0), None);
+    }
+}
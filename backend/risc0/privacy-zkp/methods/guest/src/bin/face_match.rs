@@ -0,0 +1,26 @@
+#![no_main]
+use risc0_zkvm::guest::env;
+
+use privacy_zkp_methods_guest::face::{cosine_at_least, embedding_commitment};
+use privacy_zkp_methods_guest::types::FaceMatchInput;
+
+risc0_zkvm::guest::entry!(main);
+
+pub fn main() {
+    // Read the input
+    let input: FaceMatchInput = env::read();
+
+    // 1. Recompute the registered commitment from the reference embedding
+    let reference_commitment = embedding_commitment(&input.reference, &input.reference_salt);
+
+    // 2. Compare the embeddings; malformed pairs never match
+    let matched = cosine_at_least(&input.probe, &input.reference, input.threshold_bps);
+
+    // Commit public outputs, never the embeddings themselves:
+    // - Reference commitment (to compare with the registration)
+    // - Threshold the similarity was checked against
+    // - Whether the probe matched
+    env::commit(&reference_commitment);
+    env::commit(&input.threshold_bps);
+    env::commit(&matched);
+}
@@ -0,0 +1,34 @@
+#![no_main]
+use risc0_zkvm::guest::env;
+
+use privacy_zkp_methods_guest::verify_batch;
+use privacy_zkp_methods_guest::types::{
+    BatchJournal, BatchSpendInput, JournalHeader, VerificationStatus,
+};
+
+risc0_zkvm::guest::entry!(main);
+
+pub fn main() {
+    // Read the input
+    let input: BatchSpendInput = env::read();
+
+    let (nullifiers, total_amount) = match verify_batch(&input) {
+        Ok(verified) => verified,
+        Err(status) => {
+            // Only the reason is committed for a rejected batch
+            env::commit(&JournalHeader::new(status));
+            return;
+        }
+    };
+
+    // Commit the header (status Valid) and then the public outputs in one struct
+    env::commit(&JournalHeader::new(VerificationStatus::Valid));
+    env::commit(&BatchJournal {
+        merkle_root: input.merkle_root,
+        nullifiers,
+        total_amount,
+        recipient: input.recipient,
+        relayer_fee: input.relayer_fee,
+        domain: input.domain,
+    });
+}
@@ -0,0 +1,169 @@
+// Fixed-point face embedding comparison for the face_match guest.
+//
+// Embeddings are L2-normalized Float32 vectors from the face model. They are
+// quantized to integers so the guest compares them exactly, and the reference
+// is only ever revealed as a salted hash.
+use sha2::{Digest, Sha256};
+
+// One embedding unit; model outputs lie in [-1, 1]
+pub const FIXED_POINT_SCALE: i32 = 1 << 16;
+// Longest embedding accepted, which keeps every intermediate in range of i128
+pub const MAX_EMBEDDING_LEN: usize = 4096;
+// Thresholds are cosine similarities in basis points
+pub const THRESHOLD_SCALE: u16 = 10_000;
+
+const EMBEDDING_DOMAIN: &[u8] = b"IntelliFi face embedding";
+
+// Convert a model embedding to fixed point, clamping to [-1, 1]
+pub fn quantize_embedding(embedding: &[f32]) -> Vec<i32> {
+    embedding
+        .iter()
+        .map(|&value| {
+            let value = if value.is_nan() { 0.0 } else { value.clamp(-1.0, 1.0) };
+            (value as f64 * FIXED_POINT_SCALE as f64).round() as i32
+        })
+        .collect()
+}
+
+// Salted hash of a quantized reference embedding, stored at registration
+pub fn embedding_commitment(embedding: &[i32], salt: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(EMBEDDING_DOMAIN);
+    hasher.update(salt);
+    hasher.update((embedding.len() as u32).to_be_bytes());
+    for value in embedding {
+        hasher.update(value.to_be_bytes());
+    }
+    hasher.finalize().into()
+}
+
+// Whether the embeddings can be compared: same non-zero length, within bounds
+pub fn is_valid_embedding_pair(a: &[i32], b: &[i32]) -> bool {
+    let in_range = |v: &i32| (-FIXED_POINT_SCALE..=FIXED_POINT_SCALE).contains(v);
+    !a.is_empty()
+        && a.len() == b.len()
+        && a.len() <= MAX_EMBEDDING_LEN
+        && a.iter().all(in_range)
+        && b.iter().all(in_range)
+}
+
+// Whether cosine(a, b) >= threshold_bps / THRESHOLD_SCALE, without division or
+// square roots: dot >= 0 and dot^2 * SCALE^2 >= threshold^2 * |a|^2 * |b|^2.
+// With values bounded by FIXED_POINT_SCALE and at most MAX_EMBEDDING_LEN of
+// them, each side stays below 2^115.
+pub fn cosine_at_least(a: &[i32], b: &[i32], threshold_bps: u16) -> bool {
+    if !is_valid_embedding_pair(a, b) || threshold_bps > THRESHOLD_SCALE {
+        return false;
+    }
+
+    let mut dot: i64 = 0;
+    let mut norm_a: i64 = 0;
+    let mut norm_b: i64 = 0;
+    for (&x, &y) in a.iter().zip(b) {
+        let (x, y) = (x as i64, y as i64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    // A zero vector has no direction, and a negative cosine is below every threshold
+    if norm_a == 0 || norm_b == 0 || dot < 0 {
+        return false;
+    }
+
+    let scale = THRESHOLD_SCALE as i128;
+    let threshold = threshold_bps as i128;
+    let dot = dot as i128;
+    dot * dot * scale * scale >= threshold * threshold * norm_a as i128 * norm_b as i128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: i32 = FIXED_POINT_SCALE;
+
+    #[test]
+    fn threshold_is_exact_at_the_boundary() {
+        // cos([1, 0], [0.8, 0.6]) is exactly 0.8
+        let a = [ONE, 0];
+        let b = [8_000 * 6, 6_000 * 6];
+        assert!(cosine_at_least(&a, &b, 7_999));
+        assert!(cosine_at_least(&a, &b, 8_000));
+        assert!(!cosine_at_least(&a, &b, 8_001));
+
+        // A hair under 0.8 no longer meets it
+        assert!(!cosine_at_least(&a, &[48_000, 36_001], 8_000));
+
+        assert!(cosine_at_least(&a, &a, THRESHOLD_SCALE));
+        assert!(!cosine_at_least(&a, &a, THRESHOLD_SCALE + 1));
+    }
+
+    #[test]
+    fn opposite_and_orthogonal_vectors() {
+        // A negative dot product fails even a zero threshold, whose square
+        // comparison it would otherwise pass
+        assert!(!cosine_at_least(&[ONE, 0], &[-ONE, 0], 0));
+        assert!(!cosine_at_least(&[ONE, ONE], &[-ONE, ONE / 2], 0));
+        assert!(cosine_at_least(&[ONE, 0], &[0, ONE], 0));
+        assert!(!cosine_at_least(&[ONE, 0], &[0, ONE], 1));
+    }
+
+    #[test]
+    fn rejects_unusable_pairs() {
+        assert!(!cosine_at_least(&[0, 0], &[ONE, 0], 0));
+        assert!(!cosine_at_least(&[ONE, 0], &[0, 0], 0));
+        assert!(!cosine_at_least(&[], &[], 0));
+        assert!(!cosine_at_least(&[ONE, 0], &[ONE, 0, 0], 0));
+
+        assert!(is_valid_embedding_pair(&[ONE, -ONE], &[0, 0]));
+        assert!(!is_valid_embedding_pair(&[ONE + 1, 0], &[ONE, 0]));
+        assert!(!is_valid_embedding_pair(&[ONE, 0], &[0, -ONE - 1]));
+        let too_long = vec![1; MAX_EMBEDDING_LEN + 1];
+        assert!(!is_valid_embedding_pair(&too_long, &too_long));
+    }
+
+    #[test]
+    fn quantization_clamps_to_the_unit_range() {
+        assert_eq!(
+            quantize_embedding(&[
+                2.0,
+                -3.0,
+                f32::NAN,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                0.5,
+                -0.25,
+            ]),
+            [ONE, -ONE, 0, ONE, -ONE, ONE / 2, -ONE / 4]
+        );
+        let quantized = quantize_embedding(&[1.0e30, -1.0e30]);
+        assert!(is_valid_embedding_pair(&quantized, &quantized));
+    }
+
+    #[test]
+    fn no_overflow_at_the_longest_embedding() {
+        // Every sum at its largest: this would panic in a debug build if any
+        // intermediate overflowed
+        let max = vec![ONE; MAX_EMBEDDING_LEN];
+        let min = vec![-ONE; MAX_EMBEDDING_LEN];
+        assert!(cosine_at_least(&max, &max, THRESHOLD_SCALE));
+        assert!(cosine_at_least(&min, &min, THRESHOLD_SCALE));
+        assert!(!cosine_at_least(&max, &min, 0));
+    }
+
+    #[test]
+    fn commitments_cover_salt_length_and_values() {
+        let embedding = quantize_embedding(&[0.5, -0.5]);
+        let commitment = embedding_commitment(&embedding, &[1; 32]);
+        assert_eq!(commitment, embedding_commitment(&embedding, &[1; 32]));
+        assert_ne!(commitment, embedding_commitment(&embedding, &[2; 32]));
+        assert_ne!(
+            commitment,
+            embedding_commitment(&[embedding[0], embedding[1] + 1], &[1; 32])
+        );
+        assert_ne!(
+            commitment,
+            embedding_commitment(&[embedding[0], embedding[1], 0], &[1; 32])
+        );
+    }
+}
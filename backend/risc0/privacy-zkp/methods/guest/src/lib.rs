@@ -0,0 +1,12 @@
+// Checks shared by the guest binaries (and by the host, which re-exports them
+// through privacy-zkp-methods). Spend verification itself lives in
+// privacy-zkp-core so every component hashes notes the same way.
+pub mod types;
+pub mod face;
+
+pub use types::*;
+pub use privacy_zkp_core::{
+    auth, compute_leaf_hash, derive_nullifier, pedersen, spending_key_hash, verify_amount_commitment,
+    verify_batch, verify_hidden_amount, verify_merkle_proof, verify_nullifier, verify_range,
+    verify_spend,
+};
@@ -1,37 +1,13 @@
 use serde::{Deserialize, Serialize};
-use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NullifierData {
-    pub salt: [u8; 32],            // Random salt used in nullifier generation
-    pub timestamp: u64,            // Timestamp when nullifier was generated
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AmountCommitment {
-    pub commitment: RistrettoPoint, // The Pedersen commitment
-    pub amount: u64,               // Actual amount (will be hidden)
-    pub blinding_factor: Scalar,   // Random scalar for the commitment
-}
+// Spend types live in privacy-zkp-core, shared with the host, drew-v and the napi addon
+pub use privacy_zkp_core::types::*;
 
+// A fresh embedding checked against the one committed at registration
 #[derive(Debug, Serialize, Deserialize)]
-pub struct SpendNoteInput {
-    pub wallet_address: [u8; 20],  // ETH address is 20 bytes
-    pub nullifier: [u8; 32],       // SHA256 hash is 32 bytes
-    pub amount_commitment: AmountCommitment,
-    pub nullifier_data: NullifierData,
+pub struct FaceMatchInput {
+    pub probe: Vec<i32>,           // Fixed-point embedding from the camera
+    pub reference: Vec<i32>,       // Fixed-point embedding from registration
+    pub reference_salt: [u8; 32],  // Salt of the registered embedding_commitment
+    pub threshold_bps: u16,        // Minimum cosine similarity, in basis points
 }
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MerkleProof {
-    pub path: Vec<[u8; 32]>,
-    pub indices: Vec<bool>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SpendVerificationInput {
-    pub spend_note: SpendNoteInput,
-    pub merkle_proof: MerkleProof,
-    pub merkle_root: [u8; 32],
-    pub expected_amount: u64,      // Amount to verify against commitment
-} 
\ No newline at end of file
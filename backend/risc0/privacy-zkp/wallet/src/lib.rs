@@ -0,0 +1,532 @@
+// A user's notes, kept on their machine.
+//
+// A note is only spendable by whoever holds its spending key and blinding
+// factor, so losing them loses the deposit. The wallet creates notes, keeps
+// them in a password-sealed file (see `store`), follows FaceVerifier events to
+// learn each note's leaf index and whether it has been spent, and exports
+// backups that can be merged into another wallet. Notes derived from a
+// `seed::NoteSeed` can also be found again from the chain alone with `rescan`.
+//
+// A note's leaf hash is its noteHash on chain: the backend deposits with
+// createSpendNote(compute_leaf_hash of the note, nullifier left zero), and
+// SpendNoteCreated and NoteSpent carry that value back.
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+
+pub mod seed;
+pub mod store;
+
+use seed::NoteSeed;
+
+pub use privacy_zkp_core::{Note, NullifierData, SpendDomain, SpendNoteInput};
+
+#[derive(Debug)]
+pub enum WalletError {
+    // The password is wrong, or the file was modified
+    WrongPassword,
+    // The file or backup is not one this crate wrote
+    Format(String),
+    // The note's blinding factor is not a canonical scalar
+    InvalidNote,
+    // The signature is not the wallet's over the seed message
+    InvalidSeed,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletError::WrongPassword => write!(f, "Wrong password or corrupted wallet"),
+            WalletError::Format(message) => write!(f, "{}", message),
+            WalletError::InvalidNote => write!(f, "Note blinding factor is not a canonical scalar"),
+            WalletError::InvalidSeed => write!(f, "Signature is not the wallet's signature of the seed message"),
+            WalletError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<std::io::Error> for WalletError {
+    fn from(e: std::io::Error) -> Self {
+        WalletError::Io(e)
+    }
+}
+
+// A note with what the wallet has learned about it from the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredNote {
+    pub note: Note,
+    pub leaf_hash: [u8; 32],
+    // Unix seconds the note was created or imported
+    pub created_at: u64,
+    // Position in FaceVerifier.spendNoteHashes, once its deposit is seen
+    pub leaf_index: Option<u64>,
+    // Block timestamp of the deposit
+    pub deposited_at: Option<u64>,
+    pub spent: Option<SpentNote>,
+    // Index under the wallet's seed, for derived notes
+    #[serde(default)]
+    pub derivation_index: Option<u32>,
+}
+
+impl StoredNote {
+    // The note as the guest takes it, with its nullifier on `domain`
+    pub fn spend_note(&self, domain: &SpendDomain) -> SpendNoteInput {
+        self.note.to_spend_note(domain).expect("Stored notes have canonical blinding factors")
+    }
+
+    pub fn is_spendable(&self) -> bool {
+        self.leaf_index.is_some() && self.spent.is_none()
+    }
+
+    // Keep whatever either copy has learned from the chain
+    fn merge(&mut self, other: &StoredNote) {
+        self.created_at = self.created_at.min(other.created_at);
+        self.leaf_index = self.leaf_index.or(other.leaf_index);
+        self.deposited_at = self.deposited_at.or(other.deposited_at);
+        self.derivation_index = self.derivation_index.or(other.derivation_index);
+        if self.spent.is_none() {
+            self.spent = other.spent.clone();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpentNote {
+    pub nullifier: [u8; 32],
+    pub recipient: [u8; 20],
+    pub timestamp: u64,
+}
+
+// The FaceVerifier events the wallet follows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEvent {
+    // SpendNoteCreated; `leaf_index` is its position among all SpendNoteCreated
+    // events, which is the note's index in spendNoteHashes and the tree
+    NoteCreated { wallet: [u8; 20], note_hash: [u8; 32], amount: u64, leaf_index: u64, timestamp: u64 },
+    NoteSpent { note_hash: [u8; 32], nullifier: [u8; 32], recipient: [u8; 20], timestamp: u64 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteWallet {
+    notes: Vec<StoredNote>,
+    // Next index to derive a note at
+    #[serde(default)]
+    next_index: u32,
+}
+
+// How many derivation indices past the last one found `rescan` tries, for
+// notes that were created but never deposited
+pub const RECOVERY_GAP: u32 = 20;
+
+impl NoteWallet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Create a note for `wallet_address` with a fresh spending key and
+    // blinding factor. Deposit with its leaf hash, and save the wallet first.
+    pub fn create_note(&mut self, wallet_address: [u8; 20], amount: u64) -> &StoredNote {
+        let note = Note {
+            wallet_address,
+            amount,
+            nullifier_data: NullifierData {
+                spending_key: rand::random(),
+            },
+            blinding_factor: Scalar::random(&mut rand::thread_rng()).to_bytes(),
+        };
+        self.insert(note).expect("Random scalars are canonical")
+    }
+
+    // Create the next note derived from `seed`, which `rescan` can recover
+    pub fn create_derived_note(&mut self, seed: &NoteSeed, amount: u64) -> &StoredNote {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.insert_derived(seed.note(index, amount), index)
+    }
+
+    // Add a note made elsewhere; adding one the wallet has returns the stored copy
+    pub fn insert(&mut self, note: Note) -> Result<&StoredNote, WalletError> {
+        let leaf_hash = note.leaf_hash().ok_or(WalletError::InvalidNote)?;
+        let index = match self.position(&leaf_hash) {
+            Some(index) => index,
+            None => {
+                self.notes.push(StoredNote {
+                    note,
+                    leaf_hash,
+                    created_at: now(),
+                    leaf_index: None,
+                    deposited_at: None,
+                    spent: None,
+                    derivation_index: None,
+                });
+                self.notes.len() - 1
+            }
+        };
+        Ok(&self.notes[index])
+    }
+
+    pub fn notes(&self) -> &[StoredNote] {
+        &self.notes
+    }
+
+    pub fn get(&self, leaf_hash: &[u8; 32]) -> Option<&StoredNote> {
+        self.notes.iter().find(|n| n.leaf_hash == *leaf_hash)
+    }
+
+    // Deposited notes that have not been spent
+    pub fn spendable(&self) -> impl Iterator<Item = &StoredNote> {
+        self.notes.iter().filter(|n| n.is_spendable())
+    }
+
+    pub fn balance(&self) -> u64 {
+        self.spendable().map(|n| n.note.amount).sum()
+    }
+
+    // Record an event if it is about one of our notes; returns whether it was
+    pub fn apply_event(&mut self, event: &ChainEvent) -> bool {
+        match event {
+            ChainEvent::NoteCreated { note_hash, leaf_index, timestamp, .. } => {
+                let Some(index) = self.position(note_hash) else {
+                    return false;
+                };
+                let stored = &mut self.notes[index];
+                stored.leaf_index = Some(*leaf_index);
+                stored.deposited_at = Some(*timestamp);
+                true
+            }
+            ChainEvent::NoteSpent { note_hash, nullifier, recipient, timestamp } => {
+                let Some(index) = self.position(note_hash) else {
+                    return false;
+                };
+                self.notes[index].spent = Some(SpentNote {
+                    nullifier: *nullifier,
+                    recipient: *recipient,
+                    timestamp: *timestamp,
+                });
+                true
+            }
+        }
+    }
+
+    // Find the notes derived from `seed` among `events`, which must be every
+    // SpendNoteCreated and NoteSpent in chain order, and apply all of them.
+    // Returns how many notes were recovered.
+    pub fn rescan(&mut self, seed: &NoteSeed, events: &[ChainEvent]) -> usize {
+        let mut held: HashSet<u32> = self.notes.iter().filter_map(|n| n.derivation_index).collect();
+        let mut recovered = 0;
+
+        for event in events {
+            if let ChainEvent::NoteCreated { wallet, note_hash, amount, .. } = event {
+                if *wallet == seed.wallet_address() && self.get(note_hash).is_none() {
+                    // Notes may be deposited out of the order they were derived in,
+                    // so every index not yet matched is tried
+                    let end = held.iter().max().map_or(0, |i| i + 1).max(self.next_index) + RECOVERY_GAP;
+                    let found = (0..end)
+                        .filter(|i| !held.contains(i))
+                        .find(|&i| seed.note(i, *amount).leaf_hash() == Some(*note_hash));
+                    if let Some(index) = found {
+                        self.insert_derived(seed.note(index, *amount), index);
+                        self.next_index = self.next_index.max(index + 1);
+                        held.insert(index);
+                        recovered += 1;
+                    }
+                }
+            }
+            self.apply_event(event);
+        }
+        recovered
+    }
+
+    pub fn load(path: impl AsRef<Path>, password: &str) -> Result<Self, WalletError> {
+        Self::from_sealed(&fs::read(path)?, password)
+    }
+
+    // Write through a temporary file so a failed save leaves the old wallet intact
+    pub fn save(&self, path: impl AsRef<Path>, password: &str) -> Result<(), WalletError> {
+        let path = path.as_ref();
+        let sealed = self.to_sealed(password)?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, sealed)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    // A sealed copy of every note, under its own password
+    pub fn export_backup(&self, password: &str) -> Result<Vec<u8>, WalletError> {
+        self.to_sealed(password)
+    }
+
+    // Merge a backup into this wallet; returns how many notes were new
+    pub fn import_backup(&mut self, backup: &[u8], password: &str) -> Result<usize, WalletError> {
+        let backup = Self::from_sealed(backup, password)?;
+        self.next_index = self.next_index.max(backup.next_index);
+        let mut added = 0;
+        for stored in backup.notes {
+            match self.position(&stored.leaf_hash) {
+                Some(index) => self.notes[index].merge(&stored),
+                None => {
+                    self.notes.push(stored);
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    fn to_sealed(&self, password: &str) -> Result<Vec<u8>, WalletError> {
+        let contents = zeroize::Zeroizing::new(
+            serde_json::to_vec(self).map_err(|e| WalletError::Format(e.to_string()))?,
+        );
+        store::seal(&contents, password)
+    }
+
+    fn from_sealed(sealed: &[u8], password: &str) -> Result<Self, WalletError> {
+        let contents = store::open(sealed, password)?;
+        let wallet: NoteWallet = serde_json::from_slice(&contents)
+            .map_err(|e| WalletError::Format(format!("Invalid wallet contents: {}", e)))?;
+        // The leaf hash is stored for lookups; never trust it over the note
+        for stored in &wallet.notes {
+            if stored.note.leaf_hash() != Some(stored.leaf_hash) {
+                return Err(WalletError::Format("Stored leaf hash does not match its note".to_string()));
+            }
+        }
+        Ok(wallet)
+    }
+
+    fn insert_derived(&mut self, note: Note, index: u32) -> &StoredNote {
+        let leaf_hash = self.insert(note).expect("Derived blinding factors are canonical").leaf_hash;
+        let position = self.position(&leaf_hash).expect("Just inserted");
+        let stored = &mut self.notes[position];
+        stored.derivation_index = Some(index);
+        stored
+    }
+
+    fn position(&self, leaf_hash: &[u8; 32]) -> Option<usize> {
+        self.notes.iter().position(|n| n.leaf_hash == *leaf_hash)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: [u8; 20] = [7; 20];
+    const DEPOSIT: u64 = 100_000_000_000_000_000;
+
+    fn created(note_hash: [u8; 32], leaf_index: u64) -> ChainEvent {
+        created_by(WALLET, note_hash, leaf_index)
+    }
+
+    fn created_by(wallet: [u8; 20], note_hash: [u8; 32], leaf_index: u64) -> ChainEvent {
+        ChainEvent::NoteCreated {
+            wallet,
+            note_hash,
+            amount: DEPOSIT,
+            leaf_index,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn saved_wallet_opens_only_with_its_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.wallet");
+
+        let mut wallet = NoteWallet::new();
+        let leaf_hash = wallet.create_note(WALLET, DEPOSIT).leaf_hash;
+        assert!(wallet.apply_event(&created(leaf_hash, 3)));
+        wallet.save(&path, "correct horse").unwrap();
+
+        let loaded = NoteWallet::load(&path, "correct horse").unwrap();
+        assert_eq!(loaded, wallet);
+        assert_eq!(loaded.get(&leaf_hash).unwrap().leaf_index, Some(3));
+        assert!(matches!(NoteWallet::load(&path, "battery staple"), Err(WalletError::WrongPassword)));
+
+        // Any edit to the file, header included, is caught
+        let sealed = fs::read(&path).unwrap();
+        for at in [6, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[at] ^= 1;
+            assert!(matches!(
+                NoteWallet::from_sealed(&tampered, "correct horse"),
+                Err(WalletError::WrongPassword)
+            ));
+        }
+    }
+
+    #[test]
+    fn events_track_deposits_and_spends() {
+        let mut wallet = NoteWallet::new();
+        let first = wallet.create_note(WALLET, DEPOSIT).leaf_hash;
+        let second = wallet.create_note(WALLET, DEPOSIT).leaf_hash;
+        assert_eq!(wallet.balance(), 0);
+
+        assert!(!wallet.apply_event(&created([9; 32], 0)));
+        assert!(wallet.apply_event(&created(first, 1)));
+        assert!(wallet.apply_event(&created(second, 2)));
+        assert_eq!(wallet.balance(), 2 * DEPOSIT);
+
+        let spent = ChainEvent::NoteSpent {
+            note_hash: first,
+            nullifier: [1; 32],
+            recipient: [2; 20],
+            timestamp: 1_700_000_100,
+        };
+        assert!(wallet.apply_event(&spent));
+        assert_eq!(wallet.balance(), DEPOSIT);
+        assert_eq!(wallet.spendable().map(|n| n.leaf_hash).collect::<Vec<_>>(), vec![second]);
+        assert_eq!(wallet.get(&first).unwrap().spent.as_ref().unwrap().recipient, [2; 20]);
+    }
+
+    #[test]
+    fn stored_notes_match_leaf_hash_semantics() {
+        let mut wallet = NoteWallet::new();
+        let stored = wallet.create_note(WALLET, DEPOSIT).clone();
+        let domain = SpendDomain { chain_id: 11155111, verifier_address: [3; 20] };
+
+        let spend_note = stored.spend_note(&domain);
+        assert_eq!(privacy_zkp_core::compute_leaf_hash(&spend_note), stored.leaf_hash);
+        assert!(privacy_zkp_core::verify_nullifier(&domain, &spend_note, &stored.leaf_hash));
+
+        // Inserting the same note again keeps one copy
+        wallet.insert(stored.note.clone()).unwrap();
+        assert_eq!(wallet.notes().len(), 1);
+
+        let mut invalid = stored.note;
+        invalid.blinding_factor = [0xff; 32];
+        assert!(matches!(wallet.insert(invalid), Err(WalletError::InvalidNote)));
+    }
+
+    #[test]
+    fn deposits_are_found_by_their_create_spend_note_argument() {
+        use sha3::{Digest, Keccak256};
+
+        let mut wallet = NoteWallet::new();
+        let stored = wallet.create_note(WALLET, DEPOSIT).clone();
+
+        // The leaf the backend computes and deposits with, from the note's
+        // secrets and commitment alone
+        let note = &stored.note;
+        let leaf = privacy_zkp_core::compute_leaf_hash(&SpendNoteInput {
+            wallet_address: note.wallet_address,
+            nullifier: [0; 32],
+            amount_commitment: privacy_zkp_core::amount_commitment(note.amount, note.blinding_factor).unwrap(),
+            nullifier_data: note.nullifier_data.clone(),
+        });
+        let mut calldata = Keccak256::digest(b"createSpendNote(bytes32)")[..4].to_vec();
+        calldata.extend_from_slice(&leaf);
+
+        // SpendNoteCreated echoes the argument as noteHash
+        let note_hash: [u8; 32] = calldata[4..].try_into().unwrap();
+        assert!(wallet.apply_event(&created(note_hash, 0)));
+        assert!(wallet.get(&stored.leaf_hash).unwrap().is_spendable());
+
+        // A deposit under keccak256(wallet || nullifier) would never be matched
+        let domain = SpendDomain { chain_id: 11155111, verifier_address: [3; 20] };
+        let nullifier = stored.spend_note(&domain).nullifier;
+        let legacy: [u8; 32] = Keccak256::digest([&WALLET[..], &nullifier[..]].concat()).into();
+        assert!(!wallet.apply_event(&created(legacy, 1)));
+    }
+
+    #[test]
+    fn backups_merge_into_another_wallet() {
+        let mut laptop = NoteWallet::new();
+        let shared = laptop.create_note(WALLET, DEPOSIT).clone();
+        laptop.create_note(WALLET, DEPOSIT);
+        assert!(laptop.apply_event(&created(shared.leaf_hash, 5)));
+        let backup = laptop.export_backup("backup password").unwrap();
+
+        let mut phone = NoteWallet::new();
+        phone.insert(shared.note.clone()).unwrap();
+        phone.create_note(WALLET, DEPOSIT);
+
+        assert!(matches!(phone.import_backup(&backup, "wrong"), Err(WalletError::WrongPassword)));
+        assert_eq!(phone.import_backup(&backup, "backup password").unwrap(), 1);
+        assert_eq!(phone.notes().len(), 3);
+        // The copy phone already had learns the leaf index from the backup
+        assert_eq!(phone.get(&shared.leaf_hash).unwrap().leaf_index, Some(5));
+        assert_eq!(phone.import_backup(&backup, "backup password").unwrap(), 0);
+    }
+
+    // The seed signature of a fixed key, and the address it signs for
+    fn seed_signature() -> ([u8; 20], Vec<u8>) {
+        use k256::ecdsa::SigningKey;
+        use sha3::{Digest, Keccak256};
+
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let point = key.verifying_key().to_encoded_point(false);
+        let mut wallet = [0u8; 20];
+        wallet.copy_from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..]);
+
+        // personal_sign of the message text, as the owner's wallet produces it
+        let mut signed = format!("\x19Ethereum Signed Message:\n{}", seed::SEED_MESSAGE.len()).into_bytes();
+        signed.extend_from_slice(seed::SEED_MESSAGE);
+        let digest = Keccak256::digest(&signed);
+        assert_eq!(digest[..], seed::seed_signing_hash());
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+        (wallet, signature)
+    }
+
+    #[test]
+    fn seed_only_comes_from_the_wallets_signature() {
+        let (wallet, mut signature) = seed_signature();
+        let seed = NoteSeed::from_signature(wallet, &signature).unwrap();
+        assert!(matches!(NoteSeed::from_signature([1; 20], &signature), Err(WalletError::InvalidSeed)));
+
+        // Either encoding of v gives the same notes
+        signature[64] -= 27;
+        let again = NoteSeed::from_signature(wallet, &signature).unwrap();
+        assert_eq!(again.note(4, DEPOSIT), seed.note(4, DEPOSIT));
+        assert_ne!(seed.note(4, DEPOSIT), seed.note(5, DEPOSIT));
+    }
+
+    #[test]
+    fn rescan_recovers_derived_notes_from_events() {
+        let (wallet, signature) = seed_signature();
+        let seed = NoteSeed::from_signature(wallet, &signature).unwrap();
+
+        let mut lost = NoteWallet::new();
+        let hashes: Vec<[u8; 32]> = (0..3).map(|_| lost.create_derived_note(&seed, DEPOSIT).leaf_hash).collect();
+
+        // Someone else's deposit, then ours out of derivation order; note 1
+        // was never deposited
+        let events = vec![
+            created_by([1; 20], [9; 32], 0),
+            created_by(wallet, hashes[2], 1),
+            created_by(wallet, hashes[0], 2),
+            ChainEvent::NoteSpent {
+                note_hash: hashes[2],
+                nullifier: [1; 32],
+                recipient: [2; 20],
+                timestamp: 1_700_000_100,
+            },
+        ];
+
+        let mut recovered = NoteWallet::new();
+        assert_eq!(recovered.rescan(&seed, &events), 2);
+        assert_eq!(recovered.notes().len(), 2);
+        assert_eq!(recovered.get(&hashes[0]).unwrap().leaf_index, Some(2));
+        assert_eq!(recovered.get(&hashes[0]).unwrap().derivation_index, Some(0));
+        assert!(recovered.get(&hashes[2]).unwrap().spent.is_some());
+        assert_eq!(recovered.balance(), DEPOSIT);
+
+        // New notes continue after the highest index found, and a second rescan finds nothing new
+        assert_eq!(recovered.create_derived_note(&seed, DEPOSIT).derivation_index, Some(3));
+        assert_eq!(recovered.rescan(&seed, &events), 0);
+    }
+}
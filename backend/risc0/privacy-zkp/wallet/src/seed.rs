@@ -0,0 +1,87 @@
+// Note secrets derived from the owner's wallet, so a lost wallet file can be
+// rebuilt from SpendNoteCreated events.
+//
+// The owner personal_signs SEED_MESSAGE once. Wallets sign
+// deterministically (RFC 6979), so signing it again on any device gives the
+// same signature, and r || s is the seed:
+//
+//   master    = HKDF-SHA256(salt = SEED_SALT, ikm = r || s)
+//   account   = HKDF-Expand(master, "account" || wallet_address)
+//   note i    = HKDF-Expand(account, "note" || i), 96 bytes
+//
+// The first 32 bytes of note i are its spending key, the secret its nullifier
+// is derived from; the last 64 are reduced to its blinding factor. Anyone
+// holding the signature can spend every derived note, so it is treated like
+// the wallet file's contents and never stored.
+use curve25519_dalek::scalar::Scalar;
+use hkdf::Hkdf;
+use privacy_zkp_core::auth::recover_address;
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use zeroize::Zeroizing;
+
+use crate::{Note, NullifierData, WalletError};
+
+// What the owner is asked to sign; changing it changes every derived note
+pub const SEED_MESSAGE: &[u8] =
+    b"IntelliFi note wallet seed v1. Sign this only in IntelliFi: it unlocks your private notes.";
+const SEED_SALT: &[u8] = b"IntelliFi note seed";
+
+// The digest a personal_sign of SEED_MESSAGE signs: EIP-191 over the message
+// itself, so the owner's wallet shows them the text they are signing
+pub fn seed_signing_hash() -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n");
+    hasher.update(SEED_MESSAGE.len().to_string().as_bytes());
+    hasher.update(SEED_MESSAGE);
+    hasher.finalize().into()
+}
+
+pub struct NoteSeed {
+    wallet_address: [u8; 20],
+    account: Zeroizing<[u8; 32]>,
+}
+
+impl NoteSeed {
+    // Check the 65-byte signature is `wallet_address`'s over the seed message
+    // before deriving from it; a seed from the wrong signature finds nothing
+    pub fn from_signature(wallet_address: [u8; 20], signature: &[u8]) -> Result<Self, WalletError> {
+        if recover_address(&seed_signing_hash(), signature) != Some(wallet_address) {
+            return Err(WalletError::InvalidSeed);
+        }
+
+        // v is left out so 0/1 and 27/28 encodings give the same seed
+        let master = Hkdf::<Sha256>::new(Some(SEED_SALT), &signature[..64]);
+        let mut account = Zeroizing::new([0u8; 32]);
+        let mut info = b"account".to_vec();
+        info.extend_from_slice(&wallet_address);
+        master.expand(&info, &mut account[..]).expect("32 bytes is a valid HKDF output length");
+
+        Ok(Self { wallet_address, account })
+    }
+
+    pub fn wallet_address(&self) -> [u8; 20] {
+        self.wallet_address
+    }
+
+    // The `index`th note of this wallet, for a deposit of `amount`
+    pub fn note(&self, index: u32, amount: u64) -> Note {
+        let account = Hkdf::<Sha256>::from_prk(&self.account[..]).expect("32 bytes is a valid PRK length");
+        let mut secrets = Zeroizing::new([0u8; 96]);
+        let mut info = b"note".to_vec();
+        info.extend_from_slice(&index.to_be_bytes());
+        account.expand(&info, &mut secrets[..]).expect("96 bytes is a valid HKDF output length");
+
+        let mut spending_key = [0u8; 32];
+        spending_key.copy_from_slice(&secrets[..32]);
+        let mut wide = Zeroizing::new([0u8; 64]);
+        wide.copy_from_slice(&secrets[32..]);
+
+        Note {
+            wallet_address: self.wallet_address,
+            amount,
+            nullifier_data: NullifierData { spending_key },
+            blinding_factor: Scalar::from_bytes_mod_order_wide(&wide).to_bytes(),
+        }
+    }
+}
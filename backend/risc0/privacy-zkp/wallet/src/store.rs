@@ -0,0 +1,71 @@
+// Password-sealed wallet files and backups.
+//
+// Layout: magic, version byte, 16 byte Argon2id salt, 12 byte nonce, then the
+// AES-256-GCM ciphertext of the contents with its tag. The magic, version,
+// salt and nonce are authenticated as associated data, so a header edited
+// on disk fails to open like a wrong password does.
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use zeroize::Zeroizing;
+
+use crate::WalletError;
+
+pub const MAGIC: [u8; 4] = *b"IFNW";
+pub const VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = 4 + 1 + SALT_LEN + NONCE_LEN;
+
+// Encrypt `contents` under a key derived from `password` with a fresh salt
+pub fn seal(contents: &[u8], password: &str) -> Result<Vec<u8>, WalletError> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + contents.len() + 16);
+    sealed.extend_from_slice(&MAGIC);
+    sealed.push(VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+
+    let key = derive_key(password, &salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key[..]));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: contents, aad: &sealed })
+        .map_err(|_| WalletError::Format("Encryption failed".to_string()))?;
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+// Decrypt what `seal` produced
+pub fn open(sealed: &[u8], password: &str) -> Result<Zeroizing<Vec<u8>>, WalletError> {
+    if sealed.len() < HEADER_LEN + 16 {
+        return Err(WalletError::Format("Wallet file is truncated".to_string()));
+    }
+    let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+    if header[..4] != MAGIC {
+        return Err(WalletError::Format("Not a note wallet file".to_string()));
+    }
+    if header[4] != VERSION {
+        return Err(WalletError::Format(format!("Unsupported wallet version {}", header[4])));
+    }
+    let salt = &header[5..5 + SALT_LEN];
+    let nonce = &header[5 + SALT_LEN..];
+
+    let key = derive_key(password, salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key[..]));
+    let contents = cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| WalletError::WrongPassword)?;
+    Ok(Zeroizing::new(contents))
+}
+
+// Argon2id with the crate's default cost; VERSION pins these parameters
+fn derive_key(password: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, WalletError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key[..])
+        .map_err(|e| WalletError::Format(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
@@ -1,5 +1,5 @@
 import { ethers } from 'ethers';
-import {  NullifierData } from '../types';
+import { SpendDomain } from '../zkp';
 import dotenv from 'dotenv';
 
 // Load environment variables
@@ -103,29 +103,18 @@ export class ContractManager {
   
   /**
    * Create a spend note on the blockchain
-   * @param walletAddress The wallet address of the user
-   * @param nullifierData The nullifier data generated for this transaction
+   * @param noteHash The note's leaf hash (computeLeafHash), as inserted into the Merkle tree
    * @param amount The amount to send with the spend note (in ETH)
    * @returns The transaction hash and note hash
    */
   public async createSpendNote(
-    walletAddress: string, 
-    nullifierData: NullifierData,
+    noteHash: string,
     amount: string = '0.1' // Default to 0.1 ETH to match the deployed contract
   ): Promise<{
     txHash: string;
     noteHash: string;
   }> {
     try {
-      // Create the note hash from wallet address and nullifier
-      // Remove '0x' prefix from both strings before hashing
-      const walletAddressBytes = Buffer.from(walletAddress.slice(2), 'hex');
-      const nullifierBytes = Buffer.from(nullifierData.nullifier.slice(2), 'hex');
-      
-      // Concatenate and hash
-      const combinedData = Buffer.concat([walletAddressBytes, nullifierBytes]);
-      const noteHash = ethers.keccak256(combinedData);
-      
       // Send transaction with the specified amount
       const tx = await this.contract.createSpendNote(noteHash, {
         value: ethers.parseEther(amount)
@@ -144,6 +133,19 @@ export class ContractManager {
     }
   }
   
+  /**
+   * The deployment nullifiers and spend proofs are bound to
+   * @returns The chain ID and contract address
+   */
+  public async getSpendDomain(): Promise<SpendDomain> {
+    const network = await this.provider.getNetwork();
+    const address = await this.contract.getAddress();
+    return {
+      chainId: Number(network.chainId),
+      verifierAddress: Buffer.from(address.slice(2), 'hex')
+    };
+  }
+  
   /**
    * Update the Merkle root in the contract
    * @param newRoot The new Merkle root hash
@@ -1,7 +1,15 @@
-import { generateNullifier, verifyNullifier } from './nullifier';
-import { SpendNoteMerkleTree } from './merkle';
+import { encryptNullifier, verifyNullifier } from './nullifier';
+import { SpendNoteMerkleTree, TREE_SCHEME } from './merkle';
 import { NullifierData, SpendNote, PrivacyConfig } from './types';
-import { ProofGenerator, ProofVerifier, SpendProof } from './zkp';
+import {
+    ProofGenerator,
+    ProofVerifier,
+    SpendProof,
+    NoteSecrets,
+    createNote,
+    computeLeafHash,
+    deriveNullifier
+} from './zkp';
 import { ContractManager } from './contract';
 import { SpendNoteLink } from './link';
 import { ClaimService } from './link/claim-service';
@@ -49,16 +57,25 @@ export class PrivacyManager {
         }
     }
     
-    // Generate a new spend note for a wallet with ZK proof and submit to contract
+    // Generate a new spend note for a wallet with ZK proof and submit to contract.
+    // The returned note secrets are the only way to spend it; keep them.
     public async generateSpendNote(walletAddress: string): Promise<{
         spendNote: SpendNote;
+        note: NoteSecrets;
         nullifierData: NullifierData;
         leafHash: string;
         proof: SpendProof;
         txHash: string;
     }> {
-        // Generate nullifier
-        const nullifierData = await generateNullifier(walletAddress);
+        // Create the note, then its leaf and its nullifier on this deployment,
+        // exactly as the guest will recompute them
+        const amount = BigInt(this.config.defaultAmount);
+        const note = createNote(Buffer.from(walletAddress.slice(2), 'hex'), amount);
+        const leaf = computeLeafHash(note);
+        const domain = await this.contractManager.getSpendDomain();
+        const nullifierData = encryptNullifier(
+            deriveNullifier(note.spending_key, leaf, BigInt(domain.chainId), domain.verifierAddress)
+        );
         
         // Create spend note
         const spendNote: SpendNote = {
@@ -69,27 +86,27 @@ export class PrivacyManager {
         };
         
         // Add to merkle tree
-        const leafHash = await this.merkleTree.addSpendNote(spendNote);
+        const leafHash = await this.merkleTree.addSpendNote(spendNote, leaf);
         
         // Get merkle proof for the note
-        const merkleProof = this.merkleTree.getProof(leafHash);
+        const merkleProof = this.merkleTree.getPositionedProof(leafHash);
         
         // Generate ZK proof
         const proof = await this.proofGenerator.prove_spend(
             Buffer.from(walletAddress.slice(2), 'hex'),
-            BigInt(this.config.defaultAmount),
+            amount,
             {
-                path: merkleProof.map(p => Buffer.from(p.slice(2), 'hex')),
-                indices: merkleProof.map((_, i) => i % 2 === 0) // Example indices, should match your tree logic
+                scheme: TREE_SCHEME,
+                path: merkleProof.path.map(p => Buffer.from(p.slice(2), 'hex')),
+                indices: merkleProof.indices
             },
             Buffer.from(this.getMerkleRoot().slice(2), 'hex')
         );
         
-        // Submit to contract
+        // Deposit under the same leaf hash the tree holds
         const { txHash } = await this.contractManager.createSpendNote(
-            walletAddress,
-            nullifierData,
-            ethers.formatEther(BigInt(this.config.defaultAmount).toString()) // Convert the hex amount to ETH
+            leafHash,
+            ethers.formatEther(amount) // Convert the hex amount to ETH
         );
         
         // Update the Merkle root in the contract
@@ -97,6 +114,7 @@ export class PrivacyManager {
         
         return {
             spendNote,
+            note,
             nullifierData,
             leafHash,
             proof,
@@ -116,12 +134,8 @@ export class PrivacyManager {
         // Get the merkle proof
         const merkleProof = this.merkleTree.getProof(leafHash);
         
-        // Calculate the note hash (same as in ContractManager.createSpendNote)
-        const walletAddressBytes = Buffer.from(walletAddress.slice(2), 'hex');
-        const nullifierBytes = Buffer.from(nullifierData.nullifier.slice(2), 'hex');
-        const combinedData = Buffer.concat([walletAddressBytes, nullifierBytes]);
-        const { ethers } = await import('ethers');
-        const noteHash = ethers.keccak256(combinedData);
+        // The note was deposited under its leaf hash
+        const noteHash = leafHash;
         
         // Generate the link
         const link = SpendNoteLink.generateLink(
@@ -248,7 +262,7 @@ export class PrivacyManager {
 
 // Export types
 export type { NullifierData, SpendNote, PrivacyConfig, MerkleLeaf } from './types';
-export type { SpendProof } from './zkp';
+export type { SpendProof, NoteSecrets } from './zkp';
 export { ContractManager } from './contract';
 export { SpendNoteLink } from './link';
 export { ClaimService } from './link/claim-service';
@@ -2,8 +2,17 @@ import { MerkleTree } from 'merkletreejs';
 import { sha256 } from '@noble/hashes/sha256';
 import { bytesToHex } from '@noble/hashes/utils';
 import { MerkleLeaf, SpendNote } from '../types';
+import { MerkleScheme } from '../zkp';
 import { MerkleTreeDB } from './db';
 
+// Sorted-pair SHA-256 over hashed leaves, MerkleScheme::SortedSha256 in privacy-zkp-merkle
+export const TREE_SCHEME = MerkleScheme.SortedSha256;
+
+export interface TreeProof {
+    path: string[];
+    indices: boolean[];
+}
+
 export class SpendNoteMerkleTree {
     private tree: MerkleTree;
     private leaves: MerkleLeaf[] = [];
@@ -31,37 +40,32 @@ export class SpendNoteMerkleTree {
         
         // Rebuild tree from leaves
         for (const leaf of this.leaves) {
-            const leafData = SpendNoteMerkleTree.createLeafData(leaf.spendNote);
-            this.tree.addLeaf(leafData, false);
+            this.tree.addLeaf(SpendNoteMerkleTree.leafBuffer(leaf.hash), false);
         }
     }
     
-    // Create leaf data from spend note
-    private static createLeafData(spendNote: SpendNote): Buffer {
-        const data = Buffer.concat([
-            Buffer.from(spendNote.walletAddress.slice(2), 'hex'),
-            Buffer.from(spendNote.nullifier.slice(2), 'hex'),
-            Buffer.from(spendNote.amount.slice(2), 'hex'),
-            Buffer.from(spendNote.timestamp.toString(16).padStart(16, '0'), 'hex')
-        ]);
-        return data;
+    // Leaves are note leaf hashes (computeLeafHash), already 32-byte nodes
+    private static leafBuffer(leafHash: string): Buffer {
+        return Buffer.from(leafHash.slice(2), 'hex');
     }
     
-    // Add a new spend note to the tree
-    public async addSpendNote(spendNote: SpendNote): Promise<string> {
-        const leafData = SpendNoteMerkleTree.createLeafData(spendNote);
-        const leafHash = '0x' + bytesToHex(sha256(leafData));
+    // Add a new spend note to the tree under its computeLeafHash leaf
+    public async addSpendNote(spendNote: SpendNote, leaf: Buffer): Promise<string> {
+        if (leaf.length !== 32) {
+            throw new Error('Leaf hash must be 32 bytes');
+        }
+        const leafHash = '0x' + bytesToHex(leaf);
         
-        const leaf: MerkleLeaf = {
+        const merkleLeaf: MerkleLeaf = {
             hash: leafHash,
             spendNote
         };
         
-        this.leaves.push(leaf);
-        this.tree.addLeaf(leafData, false);
+        this.leaves.push(merkleLeaf);
+        this.tree.addLeaf(Buffer.from(leaf), false);
         
         // Save to database
-        await this.db.saveSpendNote(leaf);
+        await this.db.saveSpendNote(merkleLeaf);
         await this.db.saveMerkleRoot(this.getRoot());
         
         return leafHash;
@@ -74,11 +78,25 @@ export class SpendNoteMerkleTree {
             throw new Error('Leaf not found');
         }
         
-        const leafData = SpendNoteMerkleTree.createLeafData(leaf.spendNote);
-        const proof = this.tree.getProof(leafData);
+        const proof = this.tree.getProof(SpendNoteMerkleTree.leafBuffer(leaf.hash));
         return proof.map(p => '0x' + p.data.toString('hex'));
     }
     
+    // Get a proof with sibling positions, as the Rust MerkleProof expects
+    public getPositionedProof(leafHash: string): TreeProof {
+        const leaf = this.leaves.find(l => l.hash === leafHash);
+        if (!leaf) {
+            throw new Error('Leaf not found');
+        }
+        
+        const proof = this.tree.getProof(SpendNoteMerkleTree.leafBuffer(leaf.hash));
+        return {
+            path: proof.map(p => '0x' + p.data.toString('hex')),
+            // A sibling on the right means the running node is the left child
+            indices: proof.map(p => p.position === 'right'),
+        };
+    }
+    
     // Verify if a leaf exists in the tree
     public verifyLeaf(leafHash: string, proof: string[]): boolean {
         const leaf = this.leaves.find(l => l.hash === leafHash);
@@ -86,10 +104,9 @@ export class SpendNoteMerkleTree {
             return false;
         }
         
-        const leafData = SpendNoteMerkleTree.createLeafData(leaf.spendNote);
         const bufferProof = proof.map(p => Buffer.from(p.slice(2), 'hex'));
         
-        return this.tree.verify(bufferProof, leafData, this.tree.getRoot());
+        return this.tree.verify(bufferProof, SpendNoteMerkleTree.leafBuffer(leaf.hash), this.tree.getRoot());
     }
     
     // Get the current root of the tree
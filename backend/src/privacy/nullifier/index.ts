@@ -25,7 +25,11 @@ export const generateNullifier = async (walletAddress: string): Promise<Nullifie
     ]);
     
     // Hash the combined data to create the nullifier
-    const nullifierHash = sha256(data);
+    return encryptNullifier(sha256(data), timestamp);
+};
+
+// Wrap a nullifier derived elsewhere, such as a note's deriveNullifier
+export const encryptNullifier = (nullifierHash: Uint8Array, timestamp: number = Date.now()): NullifierData => {
     const nullifier = '0x' + bytesToHex(nullifierHash);
     
     // Simple encryption for POC
@@ -4,12 +4,20 @@ import path from 'path';
 
 export interface SpendProofInput {
     walletAddress: Buffer;  // 20 bytes
+    authorization: Buffer;  // 65 byte wallet signature over the spend message
     amount: bigint;
+    spendingKey: Buffer;    // 32 byte note secret from deposit time
+    blindingFactor: Buffer; // 32 byte blinding factor of the note's amount commitment
     merkleProof: {
+        scheme?: number;    // Tree scheme version, sorted SHA-256 (2) by default
         path: Buffer[];     // Array of 32 byte buffers
         indices: boolean[]; // Direction for each level
     };
     merkleRoot: Buffer;    // 32 bytes
+    recipient: Buffer;     // 20 bytes
+    relayerFee?: bigint;
+    chainId: number;
+    verifierAddress: Buffer; // 20 bytes
 }
 
 export interface SpendProof {
@@ -17,14 +25,21 @@ export interface SpendProof {
     merkleRoot: Buffer;    // 32 bytes
     nullifier: Buffer;     // 32 bytes
     amount: bigint;
+    recipient: Buffer;     // 20 bytes
+    relayerFee: bigint;
+    chainId: number;
+    verifierAddress: Buffer; // 20 bytes
 }
 
 export class RiscZeroProver {
     private proverPath: string;
+    private cacheDir?: string;
     
-    constructor() {
+    // With `cacheDir`, retried requests reuse the receipt already proven for them
+    constructor(cacheDir?: string) {
         // Path to the RISC Zero prover binary
         this.proverPath = path.resolve(__dirname, '../../../../risc0/privacy-zkp/target/release/host');
+        this.cacheDir = cacheDir;
     }
     
     public async initialize(): Promise<void> {
@@ -40,19 +55,29 @@ export class RiscZeroProver {
     
     public async generateSpendProof(input: SpendProofInput): Promise<SpendProof> {
         // Convert input to JSON format expected by the prover
+        // (see risc0/privacy-zkp/host/README.md)
         const proofInput = {
             wallet_address: input.walletAddress.toString('hex'),
+            authorization: input.authorization.toString('hex'),
             amount: input.amount.toString(),
+            spending_key: input.spendingKey.toString('hex'),
+            blinding_factor: input.blindingFactor.toString('hex'),
             merkle_proof: {
+                scheme: input.merkleProof.scheme,
                 path: input.merkleProof.path.map(p => p.toString('hex')),
                 indices: input.merkleProof.indices,
             },
             merkle_root: input.merkleRoot.toString('hex'),
+            recipient: input.recipient.toString('hex'),
+            relayer_fee: (input.relayerFee ?? 0n).toString(),
+            chain_id: input.chainId,
+            verifier_address: input.verifierAddress.toString('hex'),
         };
         
         try {
             // Spawn the prover process
-            const process = spawn(this.proverPath, ['prove'], {
+            const args = this.cacheDir ? ['prove', '--cache-dir', this.cacheDir] : ['prove'];
+            const process = spawn(this.proverPath, args, {
                 stdio: ['pipe', 'pipe', 'pipe'],
             });
             
@@ -62,9 +87,13 @@ export class RiscZeroProver {
             
             // Collect output
             let output = '';
+            let errorOutput = '';
             process.stdout.on('data', (data) => {
                 output += data;
             });
+            process.stderr.on('data', (data) => {
+                errorOutput += data;
+            });
             
             // Wait for process to complete
             await new Promise((resolve, reject) => {
@@ -72,7 +101,7 @@ export class RiscZeroProver {
                     if (code === 0) {
                         resolve(null);
                     } else {
-                        reject(new Error(`Prover failed with code ${code}`));
+                        reject(new Error(`Prover failed with code ${code}: ${errorOutput.trim()}`));
                     }
                 });
             });
@@ -85,6 +114,10 @@ export class RiscZeroProver {
                 merkleRoot: Buffer.from(proofOutput.merkle_root, 'hex'),
                 nullifier: Buffer.from(proofOutput.nullifier, 'hex'),
                 amount: BigInt(proofOutput.amount),
+                recipient: Buffer.from(proofOutput.recipient, 'hex'),
+                relayerFee: BigInt(proofOutput.relayer_fee),
+                chainId: proofOutput.chain_id,
+                verifierAddress: Buffer.from(proofOutput.verifier_address, 'hex'),
             };
             
         } catch (error) {
@@ -100,6 +133,10 @@ export class RiscZeroProver {
                 merkle_root: proof.merkleRoot.toString('hex'),
                 nullifier: proof.nullifier.toString('hex'),
                 amount: proof.amount.toString(),
+                recipient: proof.recipient.toString('hex'),
+                relayer_fee: proof.relayerFee.toString(),
+                chain_id: proof.chainId,
+                verifier_address: proof.verifierAddress.toString('hex'),
             };
             
             // Spawn the verifier process
@@ -111,10 +148,13 @@ export class RiscZeroProver {
             process.stdin.write(JSON.stringify(verifyInput));
             process.stdin.end();
             
-            // Wait for process to complete
+            // Wait for process to complete: 0 is valid, 1 invalid, anything else an error
             const exitCode = await new Promise<number>((resolve) => {
                 process.on('close', resolve);
             });
+            if (exitCode !== 0 && exitCode !== 1) {
+                throw new Error(`Verifier failed with code ${exitCode}`);
+            }
             
             return exitCode === 0;
             
@@ -58,17 +58,14 @@ async function testContractIntegration() {
         
         // 1. Generate a spend note
         console.log(`Generating spend note for wallet: ${testWalletAddress}`);
-        const { nullifierData, txHash } = await privacyManager.generateSpendNote(testWalletAddress);
+        const { nullifierData, leafHash, txHash } = await privacyManager.generateSpendNote(testWalletAddress);
         console.log('Spend note generated successfully');
         console.log('Transaction hash:', txHash);
         console.log('Nullifier:', nullifierData.nullifier);
         console.log('Encrypted nullifier:', nullifierData.encryptedNullifier);
         
-        // 2. Create the note hash directly (same as in ContractManager.createSpendNote)
-        const walletAddressBytes = Buffer.from(testWalletAddress.slice(2), 'hex');
-        const nullifierBytes = Buffer.from(nullifierData.nullifier.slice(2), 'hex');
-        const combinedData = Buffer.concat([walletAddressBytes, nullifierBytes]);
-        const noteHash = ethers.keccak256(combinedData);
+        // 2. The note was deposited under its leaf hash
+        const noteHash = leafHash;
         
         console.log(`Getting spend note from contract with hash: ${noteHash}`);
         const contractNote = await contractManager.getSpendNote(noteHash);
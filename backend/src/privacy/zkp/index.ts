@@ -3,15 +3,22 @@ import { MockProofGenerator, MockProofVerifier } from './mock';
 import * as fs from 'fs';
 import * as path from 'path';
 
+// Merkle hashing schemes, numbered as in the privacy-zkp-merkle crate
+export enum MerkleScheme {
+    OrderedSha256 = 1,
+    SortedSha256 = 2,
+    SortedKeccak256 = 3,
+}
+
 // Types that mirror the Rust types
 export interface MerkleProof {
+    scheme: MerkleScheme;
     path: Buffer[];
-    indices: boolean[];
+    indices: boolean[];  // true when the running node is the left child
 }
 
 export interface NullifierData {
-    salt: Buffer;
-    timestamp: bigint;
+    spending_key: Buffer;  // 32-byte secret of the note owner
 }
 
 export interface AmountCommitment {
@@ -27,33 +34,229 @@ export interface SpendNoteInput {
     nullifier_data: NullifierData;
 }
 
+// Why the guest rejected a spend, as reported by privacy-zkp-host
+export enum VerificationStatus {
+    Valid = 'Valid',
+    InvalidNullifier = 'InvalidNullifier',
+    InvalidCommitment = 'InvalidCommitment',
+    InvalidMerklePath = 'InvalidMerklePath',
+    InvalidAuthorization = 'InvalidAuthorization',
+    FeeExceedsAmount = 'FeeExceedsAmount',
+    DuplicateNullifier = 'DuplicateNullifier',
+    EmptyBatch = 'EmptyBatch',
+    AmountOverflow = 'AmountOverflow',
+}
+
+export class SpendRejectedError extends Error {
+    constructor(public readonly status: VerificationStatus) {
+        super(`Spend rejected: ${status}`);
+        this.name = 'SpendRejectedError';
+    }
+}
+
+// The host reports rejections as "Spend rejected by guest: <status>"
+const toSpendError = (error: unknown): unknown => {
+    const message = error instanceof Error ? error.message : String(error);
+    const match = /Spend rejected by guest: (\w+)/.exec(message);
+    if (match && match[1] in VerificationStatus) {
+        return new SpendRejectedError(match[1] as VerificationStatus);
+    }
+    return error;
+};
+
 export interface SpendProof {
     receipt: Buffer;       // Serialized RISC Zero receipt
     merkle_root: Buffer;
     nullifier: Buffer;
     amount: bigint;
+    is_mock: boolean;      // The receipt is an unproven journal, not a proof
+}
+
+// A spend of a stored note, signed by its owner
+export interface SpendRequest {
+    wallet_address: Buffer;
+    amount: bigint;
+    spending_key: Buffer;
+    blinding_factor: Buffer;
+    authorization: Buffer;  // 65-byte signature over the spend message
+    merkle_proof: MerkleProof;
+    merkle_root: Buffer;
+    recipient: Buffer;
+    relayer_fee?: bigint;
+}
+
+// The FaceVerifier deployment proofs are generated for
+export interface SpendDomain {
+    chainId: number;
+    verifierAddress: Buffer;
+}
+
+export interface ProverOptions {
+    succinct?: boolean;
+    cacheDir?: string;
+    workers?: number;
+}
+
+// Hash of a spending key, as stored in a note's leaf. This and the
+// derivations below run the guest's own code in the native module (either
+// build), so the TS side can't drift from what the guest checks.
+export function spendingKeyHash(spendingKey: Buffer): Buffer {
+    return requireNativeModule('note derivation').spendingKeyHash(spendingKey);
+}
+
+// The one nullifier of the note with leaf hash `leaf` on the given deployment
+export function deriveNullifier(
+    spendingKey: Buffer,
+    leaf: Buffer,
+    chainId: bigint,
+    verifierAddress: Buffer
+): Buffer {
+    if (chainId > BigInt(Number.MAX_SAFE_INTEGER)) {
+        throw new Error('Chain ID too large for native module');
+    }
+    return requireNativeModule('note derivation').deriveNullifier(
+        spendingKey,
+        leaf,
+        Number(chainId),
+        verifierAddress
+    );
+}
+
+// A note and the secrets needed to spend it; whoever holds them owns the deposit
+export interface NoteSecrets {
+    wallet_address: Buffer;
+    amount: bigint;
+    spending_key: Buffer;
+    blinding_factor: Buffer;
+    commitment: Buffer;  // Pedersen commitment to the amount
+}
+
+// A fresh note for `walletAddress`, with secrets generated as the note wallet
+// generates them
+export function createNote(walletAddress: Buffer, amount: bigint): NoteSecrets {
+    const note = requireNativeModule('note creation').createNote(walletAddress, amount);
+    return {
+        wallet_address: note.walletAddress,
+        amount: note.amount,
+        spending_key: note.spendingKey,
+        blinding_factor: note.blindingFactor,
+        commitment: note.commitment,
+    };
+}
+
+// The leaf a note is inserted into the tree with, and deposited under as its
+// noteHash
+export function computeLeafHash(note: NoteSecrets): Buffer {
+    return requireNativeModule('note derivation').computeLeafHash({
+        walletAddress: note.wallet_address,
+        // The leaf does not cover the nullifier, which is derived from it
+        nullifier: Buffer.alloc(32),
+        amountCommitment: {
+            commitment: note.commitment,
+            amount: note.amount,
+            blindingFactor: note.blinding_factor,
+        },
+        nullifierData: { spendingKey: note.spending_key },
+    });
 }
 
-// Native module interfaces
+// Native module interfaces (see native/index.d.ts)
 interface NativeSpendProof {
     receipt: Buffer;
-    merkle_root: Buffer;
+    merkleRoot: Buffer;
     nullifier: Buffer;
-    amount: number;
+    amount: bigint;
+    isMock: boolean;
+}
+
+interface NativeSpendRequest {
+    walletAddress: Buffer;
+    amount: bigint;
+    spendingKey: Buffer;
+    blindingFactor: Buffer;
+    authorization: Buffer;
+    merkleProof: MerkleProof;
+    merkleRoot: Buffer;
+    recipient: Buffer;
+    relayerFee?: bigint;
+}
+
+interface NativeNewNote {
+    walletAddress: Buffer;
+    amount: bigint;
+    spendingKey: Buffer;
+    blindingFactor: Buffer;
+    commitment: Buffer;
+}
+
+interface NativeSpendNoteInput {
+    walletAddress: Buffer;
+    nullifier: Buffer;
+    amountCommitment: { commitment: Buffer; amount: bigint; blindingFactor: Buffer };
+    nullifierData: { spendingKey: Buffer };
 }
 
 interface NativeProofGenerator {
-    proveSpend: (
-        wallet_address: Buffer,
-        amount: number,
-        merkle_proof: MerkleProof,
-        merkle_root: Buffer
-    ) => Promise<NativeSpendProof>;
+    readonly isMock: boolean;
+    proveSpend: (request: NativeSpendRequest) => Promise<NativeSpendProof>;
+}
+
+export interface AttestationPolicy {
+    trustedRoots: Buffer[];
+    allowedMeasurements: Buffer[];
+    maxAgeSecs?: number;
+}
+
+export interface AttestationVerdict {
+    trusted: boolean;
+    reason?: string;
+    backend?: string;
+    measurement?: Buffer;
+    operator?: Buffer;
+    teePublicKey?: Buffer;
+}
+
+// Outcome of checking a spend proof; `reason` says why it was rejected
+export interface SpendVerdict {
+    valid: boolean;
+    reason?: string;
+    status?: string;
+    statusCode?: number;
+    receiptKind?: string;
+    imageId?: Buffer;
+    recipient?: Buffer;
+    relayerFee?: bigint;
+    chainId?: number;
+    verifierAddress?: Buffer;
+}
+
+export interface SpendVerifyOptions {
+    allowedImageIds?: Buffer[];  // Spend guest image IDs, 32 bytes each
 }
 
 interface NativeModule {
-    ProofGenerator: new () => NativeProofGenerator;
-    verifySpend: (proof: NativeSpendProof) => Promise<boolean>;
+    ProofGenerator: new (chainId: number, verifierAddress: Buffer, options?: ProverOptions) => NativeProofGenerator;
+    verifySpend: (proof: NativeSpendProof, options?: SpendVerifyOptions) => Promise<SpendVerdict>;
+    verifyAttestation: (
+        avsUrl: string,
+        rpcUrl: string,
+        verifierAddress: Buffer,
+        policy: AttestationPolicy
+    ) => Promise<AttestationVerdict>;
+    createNote: (walletAddress: Buffer, amount: bigint) => NativeNewNote;
+    computeLeafHash: (note: NativeSpendNoteInput) => Buffer;
+    spendingKeyHash: (spendingKey: Buffer) => Buffer;
+    deriveNullifier: (spendingKey: Buffer, leaf: Buffer, chainId: number, verifierAddress: Buffer) => Buffer;
+    spendMessageHash: (
+        walletAddress: Buffer,
+        nullifier: Buffer,
+        merkleRoot: Buffer,
+        amount: bigint,
+        recipient: Buffer,
+        relayerFee: bigint,
+        chainId: number,
+        verifierAddress: Buffer
+    ) => Buffer;
 }
 
 // Environment detection
@@ -94,7 +297,7 @@ const getNativeModule = async (): Promise<NativeModule | null> => {
         const nativeModule = require(modulePath);
         return nativeModule as NativeModule;
     } catch (error) {
-        // Log the error but return null to fall back to mock
+        // Log the error; callers decide whether a missing module is fatal
         console.error('Error loading native module:', error);
         return null;
     }
@@ -102,108 +305,175 @@ const getNativeModule = async (): Promise<NativeModule | null> => {
 
 // Interface to the RISC Zero proof system
 export class ProofGenerator {
-    private impl: MockProofGenerator | NativeProofGenerator;
-    private nativeLoaded = false;
-    private nativeLoading = false;
+    private native?: Promise<NativeProofGenerator>;
 
-    constructor() {
-        // Default to mock implementation
-        this.impl = new MockProofGenerator();
-        
-        // Try to load native module if in production
-        if (isProduction) {
-            this.loadNativeModule().catch(err => {
-                console.warn('Failed to load native module:', err);
-            });
-        } else {
+    // In production spends are proven by the native prover for `domain`;
+    // elsewhere proofs are mocks, marked is_mock
+    constructor(private domain?: SpendDomain, private options?: ProverOptions) {
+        if (!isProduction) {
             console.log('Using mock proof generator for development');
         }
     }
 
-    private async loadNativeModule(): Promise<void> {
-        if (this.nativeLoaded || this.nativeLoading) return;
-        
-        this.nativeLoading = true;
-        try {
-            const nativeModule = await getNativeModule();
-            if (nativeModule) {
-                this.impl = new nativeModule.ProofGenerator();
-                this.nativeLoaded = true;
-                console.log('Using native RISC Zero implementation');
-            } else {
-                console.warn('Native RISC Zero module not available, falling back to mock implementation');
-            }
-        } catch (error) {
-            console.error('Error initializing native module:', error);
-        } finally {
-            this.nativeLoading = false;
+    // The native prover, loaded before the first proof. A failed load is
+    // retried on the next proof rather than falling back to a mock.
+    private async nativeProver(): Promise<NativeProofGenerator> {
+        if (!this.native) {
+            this.native = this.loadNativeProver().catch(error => {
+                this.native = undefined;
+                throw error;
+            });
         }
+        return await this.native;
     }
 
+    private async loadNativeProver(): Promise<NativeProofGenerator> {
+        if (!this.domain) {
+            throw new Error('A spend domain is required to prove spends in production');
+        }
+        const nativeModule = await getNativeModule();
+        if (!nativeModule) {
+            throw new Error('Native RISC Zero module not available');
+        }
+        const prover = new nativeModule.ProofGenerator(this.domain.chainId, this.domain.verifierAddress, this.options);
+        if (prover.isMock) {
+            throw new Error('Native RISC Zero module is a mock build');
+        }
+        console.log('Using native RISC Zero implementation');
+        return prover;
+    }
+
+    // Placeholder proof for a note at deposit time, always a mock: a real
+    // proof needs the owner's authorization, see prove_spend_note
     async prove_spend(
         wallet_address: Buffer,
         amount: bigint,
         merkle_proof: MerkleProof,
         merkle_root: Buffer
     ): Promise<SpendProof> {
-        // If we're using the mock implementation, just pass through
-        if (this.impl instanceof MockProofGenerator) {
-            return await this.impl.prove_spend(
-                wallet_address,
-                amount,
-                merkle_proof,
-                merkle_root
-            );
-        } else {
-            // If we're using the native implementation, convert bigint to number
-            // This is a limitation of the native module
-            const amountNumber = Number(amount);
-            if (amountNumber > Number.MAX_SAFE_INTEGER) {
-                throw new Error('Amount too large for native module');
-            }
-            
-            const nativeProof = await (this.impl as NativeProofGenerator).proveSpend(
-                wallet_address,
-                amountNumber,
-                merkle_proof,
-                merkle_root
+        return await new MockProofGenerator().prove_spend(
+            wallet_address,
+            amount,
+            merkle_proof,
+            merkle_root
+        );
+    }
+
+    // Prove a spend of a stored note
+    async prove_spend_note(request: SpendRequest): Promise<SpendProof> {
+        if (!isProduction) {
+            return await new MockProofGenerator().prove_spend(
+                request.wallet_address,
+                request.amount,
+                request.merkle_proof,
+                request.merkle_root
             );
-            
-            // Convert the amount back to bigint
-            return {
-                receipt: nativeProof.receipt,
-                merkle_root: nativeProof.merkle_root,
-                nullifier: nativeProof.nullifier,
-                amount: BigInt(nativeProof.amount)
-            };
         }
+
+        const prover = await this.nativeProver();
+        let nativeProof: NativeSpendProof;
+        try {
+            nativeProof = await prover.proveSpend({
+                walletAddress: request.wallet_address,
+                amount: request.amount,
+                spendingKey: request.spending_key,
+                blindingFactor: request.blinding_factor,
+                authorization: request.authorization,
+                merkleProof: request.merkle_proof,
+                merkleRoot: request.merkle_root,
+                recipient: request.recipient,
+                relayerFee: request.relayer_fee,
+            });
+        } catch (error) {
+            throw toSpendError(error);
+        }
+
+        return {
+            receipt: nativeProof.receipt,
+            merkle_root: nativeProof.merkleRoot,
+            nullifier: nativeProof.nullifier,
+            amount: nativeProof.amount,
+            is_mock: nativeProof.isMock
+        };
     }
 }
 
 export class ProofVerifier {
-    static async verify_spend(proof: SpendProof): Promise<boolean> {
-        if (isProduction) {
-            try {
-                const nativeModule = await getNativeModule();
-                if (nativeModule) {
-                    // Convert bigint to number for the native module
-                    const nativeProof: NativeSpendProof = {
-                        receipt: proof.receipt,
-                        merkle_root: proof.merkle_root,
-                        nullifier: proof.nullifier,
-                        amount: Number(proof.amount)
-                    };
-                    
-                    return await nativeModule.verifySpend(nativeProof);
-                }
-            } catch (error) {
-                console.error('Error verifying with native module:', error);
-            }
-            
-            console.warn('Native RISC Zero module not available, falling back to mock implementation');
+    static async verify_spend(proof: SpendProof, options?: SpendVerifyOptions): Promise<boolean> {
+        return (await ProofVerifier.verify_spend_verdict(proof, options)).valid;
+    }
+
+    // In production a proof is only accepted by the native verifier; a
+    // missing module or a failed check rejects it rather than falling back
+    static async verify_spend_verdict(proof: SpendProof, options?: SpendVerifyOptions): Promise<SpendVerdict> {
+        if (!isProduction) {
+            const valid = await MockProofVerifier.verify_spend(proof);
+            return valid ? { valid, receiptKind: 'Mock' } : { valid, reason: 'Mock verifier rejected the proof' };
         }
-        
-        // Fall back to mock implementation
-        return await MockProofVerifier.verify_spend(proof);
+
+        const nativeModule = await getNativeModule();
+        if (!nativeModule) {
+            return { valid: false, reason: 'Native RISC Zero module not available' };
+        }
+
+        const nativeProof: NativeSpendProof = {
+            receipt: proof.receipt,
+            merkleRoot: proof.merkle_root,
+            nullifier: proof.nullifier,
+            amount: proof.amount,
+            isMock: proof.is_mock
+        };
+        try {
+            return await nativeModule.verifySpend(nativeProof, options);
+        } catch (error) {
+            console.error('Error verifying with native module:', error);
+            return { valid: false, reason: error instanceof Error ? error.message : String(error) };
+        }
+    }
+}
+
+// The native module, for functions that have no mock
+const requireNativeModule = (purpose: string): NativeModule => {
+    const modulePath = findNativeModule();
+    if (!modulePath) {
+        throw new Error(`Native module is required for ${purpose}`);
     }
-} 
\ No newline at end of file
+    // eslint-disable-next-line @typescript-eslint/no-var-requires, @typescript-eslint/no-require-imports
+    return require(modulePath) as NativeModule;
+};
+
+// What the note owner personal_signs to authorize spending the note with
+// `nullifier` to `recipient`; the signature is SpendRequest.authorization.
+// Built by the guest's own code so a signature always matches what it checks.
+export function spendMessageHash(
+    walletAddress: Buffer,
+    nullifier: Buffer,
+    merkleRoot: Buffer,
+    amount: bigint,
+    recipient: Buffer,
+    relayerFee: bigint,
+    domain: SpendDomain
+): Buffer {
+    return requireNativeModule('spend authorization').spendMessageHash(
+        walletAddress,
+        nullifier,
+        merkleRoot,
+        amount,
+        recipient,
+        relayerFee,
+        domain.chainId,
+        domain.verifierAddress
+    );
+}
+
+// Verify the AVS attestation before encrypting secrets to FaceVerifier.teePublicKey.
+// There is no mock fallback: without the native module nothing can be trusted.
+export async function verifyAttestation(
+    avsUrl: string,
+    rpcUrl: string,
+    verifierAddress: Buffer,
+    policy: AttestationPolicy
+): Promise<AttestationVerdict> {
+    const nativeModule = requireNativeModule('attestation verification');
+    return await nativeModule.verifyAttestation(avsUrl, rpcUrl, verifierAddress, policy);
+}
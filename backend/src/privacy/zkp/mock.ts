@@ -31,7 +31,8 @@ export class MockProofGenerator {
             receipt: mockReceipt,
             merkle_root: merkle_root,
             nullifier: nullifier,
-            amount: amount
+            amount: amount,
+            is_mock: true
         };
     }
 }
@@ -1,15 +1,31 @@
 declare module './native' {
-    import { SpendProof, MerkleProof } from './index';
-    
+    import { MerkleProof, ProverOptions, SpendVerdict, SpendVerifyOptions } from './index';
+
+    export interface SpendProof {
+        receipt: Buffer;
+        merkleRoot: Buffer;
+        nullifier: Buffer;
+        amount: bigint;
+        isMock: boolean;
+    }
+
+    export interface SpendRequest {
+        walletAddress: Buffer;
+        amount: bigint;
+        spendingKey: Buffer;
+        blindingFactor: Buffer;
+        authorization: Buffer;
+        merkleProof: MerkleProof;
+        merkleRoot: Buffer;
+        recipient: Buffer;
+        relayerFee?: bigint;
+    }
+
     export class ProofGenerator {
-        constructor();
-        prove_spend(
-            wallet_address: Buffer,
-            amount: bigint,
-            merkle_proof: MerkleProof,
-            merkle_root: Buffer
-        ): Promise<SpendProof>;
+        constructor(chainId: number, verifierAddress: Buffer, options?: ProverOptions);
+        get isMock(): boolean;
+        proveSpend(request: SpendRequest): Promise<SpendProof>;
     }
-    
-    export function verify_spend(proof: SpendProof): Promise<boolean>;
-} 
\ No newline at end of file
+
+    export function verifySpend(proof: SpendProof, options?: SpendVerifyOptions): Promise<SpendVerdict>;
+}
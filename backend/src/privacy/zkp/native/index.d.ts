@@ -4,16 +4,16 @@
 /* auto-generated by NAPI-RS */
 
 export interface MerkleProof {
+  scheme?: number
   path: Array<Buffer>
   indices: Array<boolean>
 }
 export interface NullifierData {
-  salt: Buffer
-  timestamp: number
+  spendingKey: Buffer
 }
 export interface AmountCommitment {
   commitment: Buffer
-  amount: number
+  amount: bigint
   blindingFactor: Buffer
 }
 export interface SpendNoteInput {
@@ -22,14 +22,81 @@ export interface SpendNoteInput {
   amountCommitment: AmountCommitment
   nullifierData: NullifierData
 }
+export interface PreflightReport {
+  wouldProve: boolean
+  status?: string
+  statusCode?: number
+  cycles?: number
+  segments?: number
+}
+export interface SpendRequest {
+  walletAddress: Buffer
+  amount: bigint
+  spendingKey: Buffer
+  blindingFactor: Buffer
+  authorization: Buffer
+  merkleProof: MerkleProof
+  merkleRoot: Buffer
+  recipient: Buffer
+  relayerFee?: bigint
+}
+export interface ProverOptions {
+  succinct?: boolean
+  cacheDir?: string
+  workers?: number
+}
 export interface SpendProof {
   receipt: Buffer
   merkleRoot: Buffer
   nullifier: Buffer
-  amount: number
+  amount: bigint
+  isMock: boolean
+}
+export interface NewNote {
+  walletAddress: Buffer
+  amount: bigint
+  spendingKey: Buffer
+  blindingFactor: Buffer
+  commitment: Buffer
+}
+export interface AttestationPolicy {
+  trustedRoots: Array<Buffer>
+  allowedMeasurements: Array<Buffer>
+  maxAgeSecs?: number
+}
+export interface AttestationVerdict {
+  trusted: boolean
+  reason?: string
+  backend?: string
+  measurement?: Buffer
+  operator?: Buffer
+  teePublicKey?: Buffer
+}
+export interface SpendVerifyOptions {
+  allowedImageIds?: Array<Buffer>
+}
+export interface SpendVerdict {
+  valid: boolean
+  reason?: string
+  status?: string
+  statusCode?: number
+  receiptKind?: string
+  imageId?: Buffer
+  recipient?: Buffer
+  relayerFee?: bigint
+  chainId?: number
+  verifierAddress?: Buffer
 }
-export declare function verifySpend(proof: SpendProof): Promise<boolean>
+export declare function verifySpend(proof: SpendProof, options?: SpendVerifyOptions | undefined | null): Promise<SpendVerdict>
+export declare function spendingKeyHash(spendingKey: Buffer): Buffer
+export declare function deriveNullifier(spendingKey: Buffer, leaf: Buffer, chainId: number, verifierAddress: Buffer): Buffer
+export declare function spendMessageHash(walletAddress: Buffer, nullifier: Buffer, merkleRoot: Buffer, amount: bigint, recipient: Buffer, relayerFee: bigint, chainId: number, verifierAddress: Buffer): Buffer
+export declare function createNote(walletAddress: Buffer, amount: bigint): NewNote
+export declare function computeLeafHash(note: SpendNoteInput): Buffer
+export declare function verifyAttestation(avsUrl: string, rpcUrl: string, verifierAddress: Buffer, policy: AttestationPolicy): Promise<AttestationVerdict>
 export declare class ProofGenerator {
-  constructor()
-  proveSpend(walletAddress: Buffer, amount: number, merkleProof: MerkleProof, merkleRoot: Buffer): Promise<SpendProof>
+  constructor(chainId: number, verifierAddress: Buffer, options?: ProverOptions | undefined | null)
+  get isMock(): boolean
+  proveSpend(request: SpendRequest): Promise<SpendProof>
+  preflightSpend(request: SpendRequest): Promise<PreflightReport>
 }
@@ -310,7 +310,13 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { ProofGenerator, verifySpend } = nativeBinding
+const { ProofGenerator, verifySpend, spendingKeyHash, deriveNullifier, spendMessageHash, createNote, computeLeafHash, verifyAttestation } = nativeBinding
 
 module.exports.ProofGenerator = ProofGenerator
 module.exports.verifySpend = verifySpend
+module.exports.spendingKeyHash = spendingKeyHash
+module.exports.deriveNullifier = deriveNullifier
+module.exports.spendMessageHash = spendMessageHash
+module.exports.createNote = createNote
+module.exports.computeLeafHash = computeLeafHash
+module.exports.verifyAttestation = verifyAttestation
@@ -1,27 +1,44 @@
 #![deny(clippy::all)]
 
+#[cfg(all(feature = "prover", feature = "mock"))]
+compile_error!("Features `prover` and `mock` are mutually exclusive");
+#[cfg(not(any(feature = "prover", feature = "mock")))]
+compile_error!("Enable `prover` for real proofs or `mock` for fake ones");
+
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use std::convert::TryInto;
-use sha2::{Sha256, Digest};
-use rand::Rng;
+use tee_attestation::{client, AttestationPolicy as Policy, Verdict};
+use privacy_zkp_core as zkp;
+use privacy_zkp_wallet::NoteWallet;
+
+#[cfg(feature = "prover")]
+mod prover;
+#[cfg(feature = "prover")]
+use prover as backend;
+#[cfg(all(feature = "mock", not(feature = "prover")))]
+mod mock;
+#[cfg(all(feature = "mock", not(feature = "prover")))]
+use mock as backend;
 
 #[napi(object)]
 pub struct MerkleProof {
+    // privacy-zkp-merkle scheme version, sorted-pair SHA-256 (2) when omitted
+    pub scheme: Option<u32>,
     pub path: Vec<Buffer>,
     pub indices: Vec<bool>,
 }
 
 #[napi(object)]
 pub struct NullifierData {
-    pub salt: Buffer,
-    pub timestamp: i64,
+    // 32-byte secret of the note owner; its hash is part of the leaf
+    pub spending_key: Buffer,
 }
 
 #[napi(object)]
 pub struct AmountCommitment {
     pub commitment: Buffer,
-    pub amount: i64,
+    pub amount: BigInt,
     pub blinding_factor: Buffer,
 }
 
@@ -33,55 +50,455 @@ pub struct SpendNoteInput {
     pub nullifier_data: NullifierData,
 }
 
+// JS objects are checked and converted to the privacy-zkp-core types, which
+// own the hashing, so the addon can't drift from the guest
+
+impl TryFrom<&MerkleProof> for zkp::MerkleProof {
+    type Error = Error;
+
+    fn try_from(proof: &MerkleProof) -> Result<Self> {
+        let version = proof.scheme.unwrap_or(zkp::MerkleScheme::SortedSha256.version() as u32);
+        let scheme = u8::try_from(version)
+            .ok()
+            .and_then(zkp::MerkleScheme::from_version)
+            .ok_or_else(|| Error::new(Status::InvalidArg, format!("Unknown Merkle scheme {}", version)))?;
+        Ok(zkp::MerkleProof {
+            scheme,
+            path: proof
+                .path
+                .iter()
+                .map(|node| to_array(node, "Merkle path node"))
+                .collect::<Result<_>>()?,
+            indices: proof.indices.clone(),
+        })
+    }
+}
+
+impl TryFrom<&SpendNoteInput> for zkp::SpendNoteInput {
+    type Error = Error;
+
+    fn try_from(note: &SpendNoteInput) -> Result<Self> {
+        let amount = bigint_to_u64(&note.amount_commitment.amount, "amount")?;
+        Ok(zkp::SpendNoteInput {
+            wallet_address: to_array(&note.wallet_address, "walletAddress")?,
+            nullifier: to_array(&note.nullifier, "nullifier")?,
+            amount_commitment: zkp::AmountCommitment {
+                commitment: to_array(&note.amount_commitment.commitment, "commitment")?,
+                amount,
+                blinding_factor: to_array(&note.amount_commitment.blinding_factor, "blindingFactor")?,
+            },
+            nullifier_data: zkp::NullifierData {
+                spending_key: to_array(&note.nullifier_data.spending_key, "spendingKey")?,
+            },
+        })
+    }
+}
+
+// Whether the guest would accept a spend, found without proving it
+#[napi(object)]
+pub struct PreflightReport {
+    pub would_prove: bool,
+    // What the guest committed; absent if it halted without a journal
+    pub status: Option<String>,
+    pub status_code: Option<u32>,
+    // From the zkVM executor; the mock build runs the guest's checks
+    // natively, so it has no cycle or segment counts to report
+    pub cycles: Option<i64>,
+    pub segments: Option<u32>,
+}
+
+// A spend of a stored note: the note as its owner kept it, the owner's
+// authorization, and where the funds go
+#[napi(object)]
+pub struct SpendRequest {
+    pub wallet_address: Buffer,
+    pub amount: BigInt,
+    pub spending_key: Buffer,
+    pub blinding_factor: Buffer,
+    // 65-byte wallet signature over the spend message
+    pub authorization: Buffer,
+    pub merkle_proof: MerkleProof,
+    pub merkle_root: Buffer,
+    pub recipient: Buffer,
+    pub relayer_fee: Option<BigInt>,
+}
+
+// A SpendRequest checked and converted to the core types
+pub(crate) struct Spend {
+    pub note: zkp::Note,
+    pub authorization: zkp::SpendAuthorization,
+    pub merkle_proof: zkp::MerkleProof,
+    pub merkle_root: [u8; 32],
+    pub recipient: [u8; 20],
+    pub relayer_fee: u64,
+}
+
+impl TryFrom<&SpendRequest> for Spend {
+    type Error = Error;
+
+    fn try_from(request: &SpendRequest) -> Result<Self> {
+        let note = zkp::Note {
+            wallet_address: to_array(&request.wallet_address, "walletAddress")?,
+            amount: bigint_to_u64(&request.amount, "amount")?,
+            nullifier_data: zkp::NullifierData {
+                spending_key: to_array(&request.spending_key, "spendingKey")?,
+            },
+            blinding_factor: to_array(&request.blinding_factor, "blindingFactor")?,
+        };
+        if note.leaf_hash().is_none() {
+            return Err(Error::new(Status::InvalidArg, "blindingFactor is not a canonical scalar"));
+        }
+        Ok(Spend {
+            note,
+            authorization: zkp::SpendAuthorization { signature: request.authorization.to_vec() },
+            merkle_proof: zkp::MerkleProof::try_from(&request.merkle_proof)?,
+            merkle_root: to_array(&request.merkle_root, "merkleRoot")?,
+            recipient: to_array(&request.recipient, "recipient")?,
+            relayer_fee: match &request.relayer_fee {
+                Some(fee) => bigint_to_u64(fee, "relayerFee")?,
+                None => 0,
+            },
+        })
+    }
+}
+
+#[napi(object)]
+pub struct ProverOptions {
+    // Compress receipts to succinct ones
+    pub succinct: Option<bool>,
+    // Reuse receipts proven for identical requests from this directory
+    pub cache_dir: Option<String>,
+    // How many proofs are generated at once
+    pub workers: Option<u32>,
+}
+
 #[napi(object)]
 pub struct SpendProof {
     pub receipt: Buffer,
     pub merkle_root: Buffer,
     pub nullifier: Buffer,
-    pub amount: i64,
+    pub amount: BigInt,
+    // True when the addon was built with the `mock` feature: `receipt` is
+    // the unproven journal and proves nothing
+    pub is_mock: bool,
 }
 
+// Proves spends on one FaceVerifier deployment
 #[napi]
 pub struct ProofGenerator {
-    // Mock implementation
+    domain: zkp::SpendDomain,
+    backend: backend::Backend,
 }
 
 #[napi]
 impl ProofGenerator {
     #[napi(constructor)]
-    pub fn new() -> Self {
-        ProofGenerator {}
+    pub fn new(chain_id: i64, verifier_address: Buffer, options: Option<ProverOptions>) -> Result<Self> {
+        let domain = zkp::SpendDomain {
+            chain_id: to_u64(chain_id, "chainId")?,
+            verifier_address: to_array(&verifier_address, "verifierAddress")?,
+        };
+        let options = options.unwrap_or(ProverOptions { succinct: None, cache_dir: None, workers: None });
+        Ok(ProofGenerator {
+            domain,
+            backend: backend::new(domain, &options)?,
+        })
     }
 
+    // Whether proofs from this build are fake
+    #[napi(getter, js_name = "isMock")]
+    pub fn is_mock(&self) -> bool {
+        cfg!(feature = "mock")
+    }
+
+    // Prove a spend, rejecting with "Spend rejected by guest: <status>" if the
+    // guest rejects it
     #[napi(js_name = "proveSpend")]
-    pub async fn prove_spend(
-        &self,
-        wallet_address: Buffer,
-        amount: i64,
-        merkle_proof: MerkleProof,
-        merkle_root: Buffer,
-    ) -> Result<SpendProof> {
-        // Create a mock receipt (in production this would be a real ZK proof)
-        let mut rng = rand::thread_rng();
-        let mock_receipt: Vec<u8> = (0..128).map(|_| rng.gen()).collect();
-        
-        // Create a mock nullifier based on the wallet address and amount
-        let mut hasher = Sha256::new();
-        hasher.update(&wallet_address);
-        hasher.update(amount.to_be_bytes());
-        let nullifier = hasher.finalize();
-        
-        Ok(SpendProof {
-            receipt: Buffer::from(mock_receipt),
-            merkle_root,
-            nullifier: Buffer::from(nullifier.to_vec()),
-            amount,
-        })
+    pub async fn prove_spend(&self, request: SpendRequest) -> Result<SpendProof> {
+        let spend = Spend::try_from(&request)?;
+        backend::prove(&self.backend, spend).await
+    }
+
+    // Run the guest on a spend without proving it, to reject it before
+    // spending time on a proof
+    #[napi(js_name = "preflightSpend")]
+    pub async fn preflight_spend(&self, request: SpendRequest) -> Result<PreflightReport> {
+        let spend = Spend::try_from(&request)?;
+        backend::preflight(&self.domain, spend).await
+    }
+}
+
+#[napi(object)]
+pub struct SpendVerifyOptions {
+    // Image IDs of the spend guests to accept, 32 bytes each; defaults to the
+    // guest this addon was built with
+    pub allowed_image_ids: Option<Vec<Buffer>>,
+}
+
+// Outcome of checking a spend proof. `reason` says why an invalid proof was
+// rejected; the rest is filled in as far as verification got.
+#[napi(object)]
+pub struct SpendVerdict {
+    pub valid: bool,
+    pub reason: Option<String>,
+    // What the guest committed, once the receipt verified
+    pub status: Option<String>,
+    pub status_code: Option<u32>,
+    pub receipt_kind: Option<String>,
+    // The allowed image ID the receipt verified against
+    pub image_id: Option<Buffer>,
+    // Where the funds go, to check against the transaction being submitted
+    pub recipient: Option<Buffer>,
+    pub relayer_fee: Option<BigInt>,
+    pub chain_id: Option<i64>,
+    pub verifier_address: Option<Buffer>,
+}
+
+impl SpendVerdict {
+    pub(crate) fn rejected(reason: impl Into<String>) -> Self {
+        SpendVerdict {
+            valid: false,
+            reason: Some(reason.into()),
+            status: None,
+            status_code: None,
+            receipt_kind: None,
+            image_id: None,
+            recipient: None,
+            relayer_fee: None,
+            chain_id: None,
+            verifier_address: None,
+        }
     }
 }
 
+// Fill `verdict` in from an accepted spend's journal, and accept the proof if
+// the journal commits to its root, nullifier and amount
+pub(crate) fn check_journal(mut verdict: SpendVerdict, journal: &zkp::SpendJournal, proof: &SpendProof) -> SpendVerdict {
+    let status = zkp::VerificationStatus::Valid;
+    verdict.status = Some(format!("{:?}", status));
+    verdict.status_code = Some(status.code() as u32);
+    verdict.recipient = Some(Buffer::from(journal.recipient.to_vec()));
+    verdict.relayer_fee = Some(BigInt::from(journal.relayer_fee));
+    verdict.chain_id = i64::try_from(journal.domain.chain_id).ok();
+    verdict.verifier_address = Some(Buffer::from(journal.domain.verifier_address.to_vec()));
+
+    let mismatch = if journal.merkle_root[..] != proof.merkle_root[..] {
+        Some("Merkle root does not match the journal".to_string())
+    } else if journal.nullifier[..] != proof.nullifier[..] {
+        Some("Nullifier does not match the journal".to_string())
+    } else {
+        match journal.disclosure {
+            zkp::AmountDisclosure::Revealed(amount) => match bigint_to_u64(&proof.amount, "amount") {
+                Ok(claimed) if claimed == amount => None,
+                Ok(claimed) => Some(format!("Amount {} does not match the journal's {}", claimed, amount)),
+                Err(_) => Some(format!("Amount is not a u64, the journal's is {}", amount)),
+            },
+            zkp::AmountDisclosure::Hidden { .. } => Some("The journal hides the amount".to_string()),
+        }
+    };
+    verdict.valid = mismatch.is_none();
+    verdict.reason = mismatch;
+    verdict
+}
+
+// Verify the receipt against the allowed guests and check its journal
+// commits to the proof's root, nullifier and amount
 #[napi(js_name = "verifySpend")]
-pub async fn verify_spend(_proof: SpendProof) -> Result<bool> {
-    // Mock implementation always returns true
-    Ok(true)
-} 
\ No newline at end of file
+pub async fn verify_spend(proof: SpendProof, options: Option<SpendVerifyOptions>) -> Result<SpendVerdict> {
+    let allowed_image_ids = match options.and_then(|o| o.allowed_image_ids) {
+        Some(ids) if ids.is_empty() => {
+            return Err(Error::new(Status::InvalidArg, "allowedImageIds must not be empty"));
+        }
+        Some(ids) => Some(
+            ids.iter()
+                .map(|id| to_array(id, "allowedImageIds"))
+                .collect::<Result<Vec<[u8; 32]>>>()?,
+        ),
+        None => None,
+    };
+    backend::verify(proof, allowed_image_ids).await
+}
+
+pub(crate) fn to_u64(value: i64, name: &str) -> Result<u64> {
+    u64::try_from(value).map_err(|_| Error::new(Status::InvalidArg, format!("{} must not be negative", name)))
+}
+
+pub(crate) fn bigint_to_u64(value: &BigInt, name: &str) -> Result<u64> {
+    match value.get_u64() {
+        (false, value, true) => Ok(value),
+        _ => Err(Error::new(Status::InvalidArg, format!("{} must fit in a u64", name))),
+    }
+}
+
+fn to_array<const N: usize>(bytes: &Buffer, name: &str) -> Result<[u8; N]> {
+    bytes
+        .as_ref()
+        .try_into()
+        .map_err(|_| Error::new(Status::InvalidArg, format!("{} must be {} bytes", name, N)))
+}
+
+// Hash of a spending key, as stored in a note's leaf
+#[napi(js_name = "spendingKeyHash")]
+pub fn spending_key_hash(spending_key: Buffer) -> Result<Buffer> {
+    let spending_key: [u8; 32] = to_array(&spending_key, "spendingKey")?;
+    Ok(Buffer::from(zkp::spending_key_hash(&spending_key).to_vec()))
+}
+
+// The nullifier of the note with leaf hash `leaf` on the given deployment
+#[napi(js_name = "deriveNullifier")]
+pub fn derive_nullifier(
+    spending_key: Buffer,
+    leaf: Buffer,
+    chain_id: i64,
+    verifier_address: Buffer,
+) -> Result<Buffer> {
+    let spending_key: [u8; 32] = to_array(&spending_key, "spendingKey")?;
+    let leaf: [u8; 32] = to_array(&leaf, "leaf")?;
+    let domain = zkp::SpendDomain {
+        chain_id: u64::try_from(chain_id)
+            .map_err(|_| Error::new(Status::InvalidArg, "chainId must not be negative"))?,
+        verifier_address: to_array(&verifier_address, "verifierAddress")?,
+    };
+    Ok(Buffer::from(zkp::derive_nullifier(&domain, &spending_key, &leaf).to_vec()))
+}
+
+// What the note owner personal_signs to authorize spending the note with
+// `nullifier` to `recipient`; pass the signature as SpendRequest.authorization
+#[napi(js_name = "spendMessageHash")]
+#[allow(clippy::too_many_arguments)]
+pub fn spend_message_hash(
+    wallet_address: Buffer,
+    nullifier: Buffer,
+    merkle_root: Buffer,
+    amount: BigInt,
+    recipient: Buffer,
+    relayer_fee: BigInt,
+    chain_id: i64,
+    verifier_address: Buffer,
+) -> Result<Buffer> {
+    let domain = zkp::SpendDomain {
+        chain_id: to_u64(chain_id, "chainId")?,
+        verifier_address: to_array(&verifier_address, "verifierAddress")?,
+    };
+    Ok(Buffer::from(
+        zkp::auth::spend_message_hash(
+            &to_array(&wallet_address, "walletAddress")?,
+            &to_array(&nullifier, "nullifier")?,
+            &to_array(&merkle_root, "merkleRoot")?,
+            bigint_to_u64(&amount, "amount")?,
+            &to_array(&recipient, "recipient")?,
+            bigint_to_u64(&relayer_fee, "relayerFee")?,
+            &domain,
+        )
+        .to_vec(),
+    ))
+}
+
+// A note and its secrets, as the owner keeps them, with its amount commitment
+#[napi(object)]
+pub struct NewNote {
+    pub wallet_address: Buffer,
+    pub amount: BigInt,
+    pub spending_key: Buffer,
+    pub blinding_factor: Buffer,
+    pub commitment: Buffer,
+}
+
+// A note for `walletAddress` with fresh secrets, generated as the note wallet
+// generates them. Whoever holds them can spend the deposit.
+#[napi(js_name = "createNote")]
+pub fn create_note(wallet_address: Buffer, amount: BigInt) -> Result<NewNote> {
+    let mut wallet = NoteWallet::new();
+    let note = wallet
+        .create_note(to_array(&wallet_address, "walletAddress")?, bigint_to_u64(&amount, "amount")?)
+        .note
+        .clone();
+    let commitment = zkp::amount_commitment(note.amount, note.blinding_factor)
+        .ok_or_else(|| Error::new(Status::GenericFailure, "Wallet made a non-canonical blinding factor"))?;
+    Ok(NewNote {
+        wallet_address: Buffer::from(note.wallet_address.to_vec()),
+        amount: BigInt::from(note.amount),
+        spending_key: Buffer::from(note.nullifier_data.spending_key.to_vec()),
+        blinding_factor: Buffer::from(note.blinding_factor.to_vec()),
+        commitment: Buffer::from(commitment.commitment.to_vec()),
+    })
+}
+
+// Leaf hash of a note, as inserted into the Merkle tree
+#[napi(js_name = "computeLeafHash")]
+pub fn compute_leaf_hash(note: SpendNoteInput) -> Result<Buffer> {
+    let note = zkp::SpendNoteInput::try_from(&note)?;
+    Ok(Buffer::from(zkp::compute_leaf_hash(&note).to_vec()))
+}
+
+#[napi(object)]
+pub struct AttestationPolicy {
+    pub trusted_roots: Vec<Buffer>,
+    pub allowed_measurements: Vec<Buffer>,
+    pub max_age_secs: Option<i64>,
+}
+
+#[napi(object)]
+pub struct AttestationVerdict {
+    pub trusted: bool,
+    pub reason: Option<String>,
+    pub backend: Option<String>,
+    pub measurement: Option<Buffer>,
+    pub operator: Option<Buffer>,
+    pub tee_public_key: Option<Buffer>,
+}
+
+// Check the AVS attestation before encrypting anything to its key
+#[napi(js_name = "verifyAttestation")]
+pub async fn verify_attestation(
+    avs_url: String,
+    rpc_url: String,
+    verifier_address: Buffer,
+    policy: AttestationPolicy,
+) -> Result<AttestationVerdict> {
+    let verifier: [u8; 20] = verifier_address
+        .as_ref()
+        .try_into()
+        .map_err(|_| Error::new(Status::InvalidArg, "verifierAddress must be 20 bytes"))?;
+
+    let allowed_measurements = policy
+        .allowed_measurements
+        .iter()
+        .map(|m| m.as_ref().try_into())
+        .collect::<std::result::Result<Vec<[u8; 32]>, _>>()
+        .map_err(|_| Error::new(Status::InvalidArg, "Measurements must be 32 bytes"))?;
+
+    let policy = Policy {
+        trusted_roots: policy.trusted_roots.iter().map(|r| r.to_vec()).collect(),
+        allowed_measurements,
+        max_age_secs: policy.max_age_secs.map(|s| s.max(0) as u64),
+    };
+
+    let verdict = client::verify_remote(&avs_url, &rpc_url, verifier, &policy)
+        .await
+        .map_err(|e| Error::new(Status::GenericFailure, e))?;
+
+    Ok(match verdict {
+        Verdict::Trusted {
+            backend,
+            measurement,
+            operator,
+            tee_public_key,
+        } => AttestationVerdict {
+            trusted: true,
+            reason: None,
+            backend: Some(backend),
+            measurement: Some(Buffer::from(measurement.to_vec())),
+            operator: Some(Buffer::from(operator.to_vec())),
+            tee_public_key: Some(Buffer::from(tee_public_key)),
+        },
+        Verdict::Rejected(reason) => AttestationVerdict {
+            trusted: false,
+            reason: Some(reason.to_string()),
+            backend: None,
+            measurement: None,
+            operator: None,
+            tee_public_key: None,
+        },
+    })
+}
@@ -0,0 +1,98 @@
+// Fake proofs, for developing the Node flow without the prover toolchain.
+// Every proof is marked isMock and its receipt is the journal the guest would
+// commit, unproven. verifySpend accepts mock proofs only, and only when their
+// receipt commits to the root, nullifier and amount they claim, so nothing
+// from this build passes for real and the dev flow still catches a proof
+// whose fields were mixed up.
+use napi::bindgen_prelude::*;
+use privacy_zkp_core as zkp;
+
+use crate::{check_journal, PreflightReport, ProverOptions, Spend, SpendProof, SpendVerdict};
+
+const MOCK_RECEIPT_TAG: &[u8] = b"IntelliFi mock receipt";
+
+// The deployment proofs are for, as the real backend's generator holds it
+pub type Backend = zkp::SpendDomain;
+
+pub fn new(domain: zkp::SpendDomain, _options: &ProverOptions) -> Result<Backend> {
+    Ok(domain)
+}
+
+pub async fn prove(domain: &Backend, spend: Spend) -> Result<SpendProof> {
+    let amount = spend.note.amount;
+    let input = spend_input(domain, spend)?;
+
+    // Reject what the guest would, with the prover's message, so the Node
+    // flow fails the same way in development
+    let disclosure = zkp::verify_spend(&input)
+        .map_err(|status| Error::new(Status::GenericFailure, format!("Spend rejected by guest: {:?}", status)))?;
+    let journal = zkp::SpendJournal {
+        merkle_root: input.merkle_root,
+        nullifier: input.spend_note.nullifier,
+        disclosure,
+        recipient: input.recipient,
+        relayer_fee: input.relayer_fee,
+        domain: input.domain,
+    };
+    let mut receipt = MOCK_RECEIPT_TAG.to_vec();
+    receipt.extend(zkp::to_canonical_bytes(&journal).map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?);
+
+    Ok(SpendProof {
+        receipt: Buffer::from(receipt),
+        merkle_root: Buffer::from(input.merkle_root.to_vec()),
+        nullifier: Buffer::from(input.spend_note.nullifier.to_vec()),
+        amount: BigInt::from(amount),
+        is_mock: true,
+    })
+}
+
+// The guest's checks, run natively
+pub async fn preflight(domain: &zkp::SpendDomain, spend: Spend) -> Result<PreflightReport> {
+    let input = spend_input(domain, spend)?;
+    let status = match zkp::verify_spend(&input) {
+        Ok(_) => zkp::VerificationStatus::Valid,
+        Err(status) => status,
+    };
+    Ok(PreflightReport {
+        would_prove: status.is_valid(),
+        status: Some(format!("{:?}", status)),
+        status_code: Some(status.code() as u32),
+        cycles: None,
+        segments: None,
+    })
+}
+
+// The guest input for a spend revealing its amount
+fn spend_input(domain: &zkp::SpendDomain, spend: Spend) -> Result<zkp::SpendVerificationInput> {
+    let spend_note = spend
+        .note
+        .to_spend_note(domain)
+        .ok_or_else(|| Error::new(Status::InvalidArg, "blindingFactor is not a canonical scalar"))?;
+    Ok(zkp::SpendVerificationInput {
+        spend_note,
+        merkle_proof: spend.merkle_proof,
+        merkle_root: spend.merkle_root,
+        amount_mode: zkp::AmountMode::Reveal { expected_amount: spend.note.amount },
+        authorization: spend.authorization,
+        recipient: spend.recipient,
+        relayer_fee: spend.relayer_fee,
+        domain: *domain,
+    })
+}
+
+pub async fn verify(proof: SpendProof, _allowed_image_ids: Option<Vec<[u8; 32]>>) -> Result<SpendVerdict> {
+    if !proof.is_mock {
+        return Ok(SpendVerdict::rejected("The mock build only verifies mock proofs"));
+    }
+    let verdict = SpendVerdict { receipt_kind: Some("Mock".to_string()), ..SpendVerdict::rejected("") };
+    let Some(encoded) = proof.receipt.strip_prefix(MOCK_RECEIPT_TAG) else {
+        return Ok(SpendVerdict { reason: Some("Not a mock receipt".to_string()), ..verdict });
+    };
+    let journal: zkp::SpendJournal = match zkp::from_canonical_bytes(encoded) {
+        Ok(journal) => journal,
+        Err(e) => {
+            return Ok(SpendVerdict { reason: Some(format!("Mock receipt could not be decoded: {}", e)), ..verdict })
+        }
+    };
+    Ok(check_journal(verdict, &journal, &proof))
+}
@@ -0,0 +1,174 @@
+// Real proofs, from the privacy-zkp-host proving service. Proofs are generated
+// on the service's worker threads, and the async functions wait for them on
+// tokio's blocking pool, off the JS thread.
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use privacy_zkp_core as zkp;
+use privacy_zkp_host::cache::{CachePolicy, ReceiptCache};
+use privacy_zkp_host::service::{proof_service, Priority, ProofOutput, ProofRequest, ProofService, ServiceConfig};
+use privacy_zkp_host::{decode_spend_journal, ProofGenerator, ReceiptKind, SpendError, SpendIntent, GUEST_ID};
+
+use crate::{check_journal, PreflightReport, ProverOptions, Spend, SpendProof, SpendVerdict};
+
+pub type Backend = Arc<ProofService>;
+
+pub fn new(domain: zkp::SpendDomain, options: &ProverOptions) -> Result<Backend> {
+    let mut generator = ProofGenerator::new(domain).with_compression(options.succinct.unwrap_or(false));
+    if let Some(dir) = &options.cache_dir {
+        let cache = ReceiptCache::open(dir, CachePolicy::default()).map_err(failure)?;
+        generator = generator.with_cache(Arc::new(cache));
+    }
+
+    // Budget memory for as many jobs as there are workers
+    let workers = options.workers.unwrap_or(1).max(1) as usize;
+    let defaults = ServiceConfig::default();
+    let config = ServiceConfig {
+        workers,
+        memory_budget: defaults.job_memory * workers as u64,
+        ..defaults
+    };
+    Ok(Arc::new(proof_service(config, generator)))
+}
+
+pub async fn prove(service: &Backend, spend: Spend) -> Result<SpendProof> {
+    let request = ProofRequest::Spend {
+        note: spend.note,
+        authorization: spend.authorization,
+        intent: SpendIntent {
+            recipient: spend.recipient,
+            relayer_fee: spend.relayer_fee,
+            hidden: None,
+        },
+        merkle_proof: spend.merkle_proof,
+        merkle_root: spend.merkle_root,
+    };
+    let id = service.submit(request, Priority::Normal);
+
+    let service = Arc::clone(service);
+    let output = tokio::task::spawn_blocking(move || service.wait(id))
+        .await
+        .map_err(failure)?
+        .map_err(failure)?;
+    let ProofOutput::Spend(proof) = output else {
+        return Err(failure("Prover returned a batch proof for a spend"));
+    };
+    let amount = proof.amount.ok_or_else(|| failure("Spend proof hides its amount"))?;
+
+    Ok(SpendProof {
+        receipt: Buffer::from(bincode::serialize(&proof.receipt).map_err(failure)?),
+        merkle_root: Buffer::from(proof.merkle_root.to_vec()),
+        nullifier: Buffer::from(proof.nullifier.to_vec()),
+        amount: BigInt::from(amount),
+        is_mock: false,
+    })
+}
+
+// Run the spend guest in the executor, on the blocking pool like proving
+pub async fn preflight(domain: &zkp::SpendDomain, spend: Spend) -> Result<PreflightReport> {
+    let generator = ProofGenerator::new(*domain);
+    let report = tokio::task::spawn_blocking(move || {
+        let intent = SpendIntent {
+            recipient: spend.recipient,
+            relayer_fee: spend.relayer_fee,
+            hidden: None,
+        };
+        generator.preflight(&spend.note, spend.authorization, intent, spend.merkle_proof, spend.merkle_root)
+    })
+    .await
+    .map_err(failure)?
+    .map_err(failure)?;
+
+    Ok(PreflightReport {
+        would_prove: report.would_prove(),
+        status: report.status.map(|status| format!("{:?}", status)),
+        status_code: report.status.map(|status| status.code() as u32),
+        cycles: Some(i64::try_from(report.cycles).map_err(failure)?),
+        segments: Some(u32::try_from(report.segments).map_err(failure)?),
+    })
+}
+
+// Check the receipt, then that its journal commits to what the proof claims.
+// Verification runs on tokio's blocking pool; a composite receipt can take
+// a while.
+pub async fn verify(proof: SpendProof, allowed_image_ids: Option<Vec<[u8; 32]>>) -> Result<SpendVerdict> {
+    if proof.is_mock {
+        return Ok(SpendVerdict::rejected("Mock proofs prove nothing"));
+    }
+    let allowed_image_ids = allowed_image_ids.unwrap_or_else(|| vec![image_id_bytes(GUEST_ID)]);
+    tokio::task::spawn_blocking(move || check(&proof, &allowed_image_ids))
+        .await
+        .map_err(failure)
+}
+
+fn check(proof: &SpendProof, allowed_image_ids: &[[u8; 32]]) -> SpendVerdict {
+    let receipt = match bincode::deserialize(&proof.receipt) {
+        Ok(receipt) => receipt,
+        Err(e) => return SpendVerdict::rejected(format!("Receipt could not be deserialized: {}", e)),
+    };
+    let receipt_kind = match ReceiptKind::of(&receipt) {
+        Ok(kind) => kind,
+        Err(e) => return SpendVerdict::rejected(e),
+    };
+
+    // The first allowed guest the receipt verifies against
+    let mut last_error = String::new();
+    let image_id = allowed_image_ids.iter().find(|id| match receipt.verify(image_id_words(id)) {
+        Ok(()) => true,
+        Err(e) => {
+            last_error = e.to_string();
+            false
+        }
+    });
+    let Some(image_id) = image_id else {
+        return SpendVerdict {
+            receipt_kind: Some(format!("{:?}", receipt_kind)),
+            ..SpendVerdict::rejected(format!(
+                "Receipt does not verify against an allowed image ID: {}",
+                last_error
+            ))
+        };
+    };
+
+    let mut verdict = SpendVerdict {
+        receipt_kind: Some(format!("{:?}", receipt_kind)),
+        image_id: Some(Buffer::from(image_id.to_vec())),
+        ..SpendVerdict::rejected("")
+    };
+    let journal = match decode_spend_journal(&receipt) {
+        Ok(journal) => journal,
+        Err(SpendError::Rejected(status)) => {
+            verdict.status = Some(format!("{:?}", status));
+            verdict.status_code = Some(status.code() as u32);
+            verdict.reason = Some(format!("Guest rejected the spend: {:?}", status));
+            return verdict;
+        }
+        Err(SpendError::Prover(e)) => {
+            verdict.reason = Some(format!("Journal could not be decoded: {}", e));
+            return verdict;
+        }
+    };
+
+    check_journal(verdict, &journal, proof)
+}
+
+// Image IDs cross the JS boundary as the digest's bytes, its words little-endian
+fn image_id_bytes(words: [u32; 8]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, word) in words.iter().enumerate() {
+        bytes[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+fn image_id_words(bytes: &[u8; 32]) -> [u32; 8] {
+    let mut words = [0u32; 8];
+    for (i, word) in words.iter_mut().enumerate() {
+        *word = u32::from_le_bytes([bytes[i * 4], bytes[i * 4 + 1], bytes[i * 4 + 2], bytes[i * 4 + 3]]);
+    }
+    words
+}
+
+fn failure(e: impl std::fmt::Display) -> Error {
+    Error::new(Status::GenericFailure, e.to_string())
+}
@@ -3,43 +3,128 @@ const { ProofGenerator, verifySpend } = require('./index');
 async function runTest() {
   try {
     console.log('Testing native RISC Zero module...');
-    
-    // Create a proof generator
-    const generator = new ProofGenerator();
-    console.log('Created ProofGenerator');
-    
-    // Create test data
-    const walletAddress = Buffer.from('0x1234567890123456789012345678901234567890'.slice(2), 'hex');
-    const amount = 100; // Use a regular number instead of BigInt
-    const merkleRoot = Buffer.from('0000000000000000000000000000000000000000000000000000000000000000', 'hex');
-    const merkleProof = {
-      path: [
-        Buffer.from('0000000000000000000000000000000000000000000000000000000000000000', 'hex'),
-        Buffer.from('0000000000000000000000000000000000000000000000000000000000000000', 'hex')
-      ],
-      indices: [false, true]
+
+    const verifierAddress = Buffer.alloc(20, 3);
+    const generator = new ProofGenerator(11155111, verifierAddress);
+    console.log(`Created ProofGenerator (${generator.isMock ? 'mock' : 'prover'} build)`);
+
+    // A spend nobody signed: the guest must reject it
+    const request = {
+      walletAddress: Buffer.from('1234567890123456789012345678901234567890', 'hex'),
+      amount: 100n,
+      spendingKey: Buffer.alloc(32, 1),
+      blindingFactor: Buffer.alloc(32, 0),
+      authorization: Buffer.alloc(65, 0),
+      merkleProof: {
+        path: [Buffer.alloc(32, 0), Buffer.alloc(32, 0)],
+        indices: [false, true]
+      },
+      merkleRoot: Buffer.alloc(32, 0),
+      recipient: Buffer.alloc(20, 9)
+    };
+
+    console.log('Proving an unauthorized spend...');
+    try {
+      await generator.proveSpend(request);
+      throw new Error('Unauthorized spend was proven');
+    } catch (error) {
+      if (!/Spend rejected by guest: InvalidAuthorization/.test(error.message)) {
+        throw error;
+      }
+      console.log('Rejected:', error.message);
+    }
+
+    // Amounts are u64 wei: above 2^53 is fine, above 2^64 is not
+    try {
+      await generator.proveSpend({ ...request, amount: 1n << 64n });
+      throw new Error('An amount above u64 was accepted');
+    } catch (error) {
+      if (!/amount must fit in a u64/.test(error.message)) {
+        throw error;
+      }
+    }
+
+    // Random bytes are not a receipt
+    console.log('Verifying a forged proof...');
+    const forged = {
+      receipt: Buffer.alloc(128, 7),
+      merkleRoot: request.merkleRoot,
+      nullifier: Buffer.alloc(32, 0),
+      amount: request.amount,
+      isMock: false
     };
-    
-    // Generate a proof
-    console.log('Generating proof...');
-    const proof = await generator.proveSpend(walletAddress, amount, merkleProof, merkleRoot);
-    console.log('Generated proof:', proof);
-    
-    // Verify the proof
-    console.log('Verifying proof...');
-    const isValid = await verifySpend(proof);
-    console.log('Proof verification result:', isValid);
-    
-    if (isValid) {
-      console.log('✅ Test passed!');
-    } else {
-      console.log('❌ Test failed: Proof verification returned false');
+    const verdict = await verifySpend(forged);
+    if (verdict.valid || !verdict.reason) {
+      console.log('❌ Test failed: a forged proof verified');
       process.exit(1);
     }
+    console.log('Rejected:', verdict.reason);
+
+    // A mock receipt is the journal it claims, unproven; verifySpend holds
+    // mock proofs to it
+    if (generator.isMock) {
+      console.log('Verifying mock proofs...');
+      const mock = mockProof({ ...request, nullifier: Buffer.alloc(32, 2) }, verifierAddress);
+      const accepted = await verifySpend(mock);
+      if (!accepted.valid) {
+        throw new Error(`A consistent mock proof was rejected: ${accepted.reason}`);
+      }
+      for (const tampered of [
+        { ...mock, nullifier: Buffer.alloc(32, 5) },
+        { ...mock, amount: request.amount + 1n },
+        { ...mock, receipt: Buffer.alloc(128, 7) }
+      ]) {
+        const verdict = await verifySpend(tampered);
+        if (verdict.valid || !verdict.reason) {
+          throw new Error('An inconsistent mock proof verified');
+        }
+        console.log('Rejected:', verdict.reason);
+      }
+    }
+
+    // Image IDs must be 32 bytes
+    try {
+      await verifySpend(forged, { allowedImageIds: [Buffer.alloc(31)] });
+      throw new Error('A short image ID was accepted');
+    } catch (error) {
+      if (!/allowedImageIds must be 32 bytes/.test(error.message)) {
+        throw error;
+      }
+    }
+    console.log('✅ Test passed!');
   } catch (error) {
     console.error('❌ Test failed with error:', error);
     process.exit(1);
   }
 }
 
-runTest(); 
\ No newline at end of file
+// The mock build's receipt: a tag, then the spend journal in the canonical
+// (postcard) encoding
+function mockProof(spend, verifierAddress) {
+  const varint = (value) => {
+    const bytes = [];
+    let v = BigInt(value);
+    do {
+      bytes.push(Number(v & 0x7fn) | (v > 0x7fn ? 0x80 : 0));
+      v >>= 7n;
+    } while (v > 0n);
+    return Buffer.from(bytes);
+  };
+  const journal = Buffer.concat([
+    spend.merkleRoot,
+    spend.nullifier,
+    varint(0), varint(spend.amount), // AmountDisclosure::Revealed
+    spend.recipient,
+    varint(0), // relayer fee
+    varint(11155111), verifierAddress
+  ]);
+  return {
+    receipt: Buffer.concat([Buffer.from('IntelliFi mock receipt'), journal]),
+    merkleRoot: spend.merkleRoot,
+    nullifier: spend.nullifier,
+    amount: spend.amount,
+    isMock: true
+  };
+}
+
+runTest();
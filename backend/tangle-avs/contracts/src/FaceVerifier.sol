@@ -43,6 +43,9 @@ contract FaceVerifier {
     // AVS address that can verify proofs
     address public avsAddress;
     
+    // Latest attestation binding teePublicKey to a measured AVS build
+    bytes public teeAttestation;
+    
     // Events
     event Registered(
         address indexed wallet,
@@ -75,12 +78,31 @@ contract FaceVerifier {
     event CommitmentCreated(
         bytes32 indexed noteHash,
         bytes encryptedNullifier,
-        bytes encryptedPrivateKey,
+        bytes encryptedAuthorization,
         bytes encryptedIndex,
         bytes encryptedMerklePath,
+        address recipient,
+        uint256 relayerFee,
+        uint256 timestamp
+    );
+    
+    event TeeAttestationPublished(
+        bytes32 measurement,
+        bytes teePublicKey,
+        address operator,
         uint256 timestamp
     );
     
+    // Attestation layout produced by drew-v (see src/attestation.rs)
+    struct OnchainAttestation {
+        uint16 version;
+        bytes32 measurement;
+        bytes teePublicKey;
+        address operator;
+        uint64 timestamp;
+        bytes signature;
+    }
+    
     constructor(bytes memory _teePublicKey) {
         teePublicKey = _teePublicKey;
         avsAddress = msg.sender; // Initially set to deployer, can be updated
@@ -106,6 +128,31 @@ contract FaceVerifier {
         teePublicKey = _teePublicKey;
     }
     
+    /**
+     * @notice Publish the AVS attestation document for the current TEE key
+     * @param _attestation ABI-encoded OnchainAttestation
+     * @dev Clients verify the signature chain off-chain; the contract only
+     *      checks that the attested key is the one users will encrypt to.
+     */
+    function publishAttestation(bytes calldata _attestation) external {
+        require(msg.sender == avsAddress, "Not authorized");
+        
+        OnchainAttestation memory attestation = abi.decode(_attestation, (OnchainAttestation));
+        require(
+            keccak256(attestation.teePublicKey) == keccak256(teePublicKey),
+            "Attested key does not match teePublicKey"
+        );
+        
+        teeAttestation = _attestation;
+        
+        emit TeeAttestationPublished(
+            attestation.measurement,
+            attestation.teePublicKey,
+            attestation.operator,
+            attestation.timestamp
+        );
+    }
+    
     /**
      * @notice Register a user's face hash along with their public key and IPFS hash.
      * @param _faceHash The hash of the user's facial data (computed off-chain).
@@ -170,28 +217,35 @@ contract FaceVerifier {
      * @notice Create a commitment for spending a note with encrypted data for TEE
      * @param _noteHash The hash of the spend note
      * @param _encryptedNullifier The nullifier encrypted with TEE public key
-     * @param _encryptedPrivateKey The private key encrypted with TEE public key
+     * @param _encryptedAuthorization The wallet address and its signed spend authorization, encrypted with TEE public key
      * @param _encryptedIndex The index encrypted with TEE public key
      * @param _encryptedMerklePath The merkle path encrypted with TEE public key
+     * @param _recipient The address the proof will release the funds to
+     * @param _relayerFee The part of the note amount paid to the submitter
      */
     function createCommitment(
         bytes32 _noteHash,
         bytes calldata _encryptedNullifier,
-        bytes calldata _encryptedPrivateKey,
+        bytes calldata _encryptedAuthorization,
         bytes calldata _encryptedIndex,
-        bytes calldata _encryptedMerklePath
+        bytes calldata _encryptedMerklePath,
+        address _recipient,
+        uint256 _relayerFee
     ) external {
         // Ensure the note exists and hasn't been spent
         require(spendNotes[_noteHash].noteHash != bytes32(0), "Note does not exist");
         require(!spendNotes[_noteHash].spent, "Note already spent");
+        require(_relayerFee <= spendNotes[_noteHash].amount, "Relayer fee exceeds amount");
         
         // Emit event for AVS to pick up
         emit CommitmentCreated(
             _noteHash,
             _encryptedNullifier,
-            _encryptedPrivateKey,
+            _encryptedAuthorization,
             _encryptedIndex,
             _encryptedMerklePath,
+            _recipient,
+            _relayerFee,
             block.timestamp
         );
     }
@@ -215,13 +269,16 @@ contract FaceVerifier {
      * @notice Spend a note by providing the nullifier and ZK proof from TEE
      * @param _noteHash The hash of the spend note
      * @param _nullifier The nullifier associated with the note
-     * @param _recipient The address to send the funds to
-     * @param _zkProof The ZK proof from RiscZero verifying the merkle path
+     * @param _recipient The address to send the funds to, as committed in the proof journal
+     * @param _relayerFee The part of the amount paid to the caller, as committed in the proof journal
+     * @param _zkProof The ZK proof from RiscZero verifying the merkle path, as
+     *        abi.encode(bytes seal, bytes journal) (SolidityProof in privacy-zkp-host)
      */
     function spendNoteWithProof(
         bytes32 _noteHash,
         bytes32 _nullifier,
         address payable _recipient,
+        uint256 _relayerFee,
         bytes calldata _zkProof
     ) external {
         // Only AVS can call this function
@@ -243,10 +300,15 @@ contract FaceVerifier {
         // Mark nullifier as spent
         spentNullifiers[_nullifier] = true;
         
-        // Transfer funds to recipient
+        // Transfer funds to recipient, less the relayer fee
         uint256 amount = spendNotes[_noteHash].amount;
-        (bool success, ) = _recipient.call{value: amount}("");
+        require(_relayerFee <= amount, "Relayer fee exceeds amount");
+        (bool success, ) = _recipient.call{value: amount - _relayerFee}("");
         require(success, "Transfer failed");
+        if (_relayerFee > 0) {
+            (bool paid, ) = payable(msg.sender).call{value: _relayerFee}("");
+            require(paid, "Relayer fee transfer failed");
+        }
         
         // Emit event
         emit NoteSpent(_noteHash, _nullifier, _recipient, block.timestamp);
@@ -258,6 +320,7 @@ contract FaceVerifier {
      * @param _proof The Merkle proof.
      * @return True if the proof is valid, false otherwise.
      * @dev This is a simplified implementation. In production, use a proper Merkle proof verification.
+     *      Sorted-pair keccak256, MerkleScheme::SortedKeccak256 in privacy-zkp-merkle.
      */
     function verifyMerkleProof(bytes32 _leaf, bytes32[] calldata _proof) internal view returns (bool) {
         bytes32 computedHash = _leaf;
@@ -0,0 +1,147 @@
+use blueprint_sdk::logging::{info, warn};
+use blueprint_sdk::std::sync::Arc;
+use blueprint_sdk::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use blueprint_sdk::tokio::net::{TcpListener, TcpStream};
+use tee_attestation::AttestationDocument;
+
+use crate::attestation::to_onchain;
+use crate::threshold::{ShareError, ShareRequest, ThresholdContext};
+
+// Default address for the attestation API, override with ATTESTATION_API_ADDR
+pub const DEFAULT_API_ADDR: &str = "0.0.0.0:9633";
+
+// What the API serves
+#[derive(Clone)]
+pub struct ApiState {
+    pub document: Arc<AttestationDocument>,
+    pub threshold: Option<ThresholdContext>,
+}
+
+// Serve the API until the listener fails
+//
+//   GET /attestation                    -> JSON document
+//   GET /attestation/onchain            -> hex of the publishAttestation calldata argument
+//   GET /decryption-share/<ephemeral>?requester=<i>&commitment=<hex>&response=<hex>
+//                                       -> this operator's share for an on-chain payload,
+//                                          sealed to the committee member that signed the request
+pub async fn serve(addr: String, state: ApiState) -> std::io::Result<()> {
+    let listener = TcpListener::bind(&addr).await?;
+    info!("Attestation API listening on {}", addr);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let state = state.clone();
+        blueprint_sdk::tokio::spawn(async move {
+            if let Err(e) = handle(stream, &state).await {
+                warn!("Attestation API request from {} failed: {:?}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: &ApiState) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.lines().next().and_then(|line| {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", path, ..] => Some(path.to_string()),
+            _ => None,
+        }
+    });
+
+    let (status, body) = match path.as_deref() {
+        Some("/attestation") => (
+            "200 OK",
+            serde_json::to_string(state.document.as_ref())
+                .expect("Attestation document is serializable"),
+        ),
+        Some("/attestation/onchain") => (
+            "200 OK",
+            serde_json::json!({ "attestation": to_onchain(&state.document).to_string() })
+                .to_string(),
+        ),
+        Some(path) if path.starts_with("/decryption-share/") => {
+            decryption_share(state.threshold.as_ref(), &path["/decryption-share/".len()..])
+        }
+        _ => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn decryption_share(threshold: Option<&ThresholdContext>, target: &str) -> (&'static str, String) {
+    let Some(threshold) = threshold else {
+        return (
+            "404 Not Found",
+            r#"{"error":"not a committee member"}"#.to_string(),
+        );
+    };
+    let (ephemeral_hex, query) = target.split_once('?').unwrap_or((target, ""));
+    let Ok(ephemeral) = hex::decode(ephemeral_hex) else {
+        return (
+            "400 Bad Request",
+            r#"{"error":"invalid ephemeral"}"#.to_string(),
+        );
+    };
+    let request = ShareRequest::from_query(query).ok();
+
+    match threshold.share_for(&ephemeral, request.as_ref()) {
+        Ok(sealed) => (
+            "200 OK",
+            serde_json::to_string(&sealed).expect("Sealed share is serializable"),
+        ),
+        Err(e @ ShareError::Unauthorized(_)) => (
+            "401 Unauthorized",
+            serde_json::json!({ "error": e.to_string() }).to_string(),
+        ),
+        // Unknown ephemerals may simply not have reached us yet; the caller retries
+        Err(e @ ShareError::NotObserved) => (
+            "404 Not Found",
+            serde_json::json!({ "error": e.to_string() }).to_string(),
+        ),
+        Err(e @ ShareError::Invalid(_)) => (
+            "400 Bad Request",
+            serde_json::json!({ "error": e.to_string() }).to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threshold::{deal, encrypt, finalize};
+
+    #[test]
+    fn test_decryption_share_requires_a_member_signature() {
+        let (dealing_1, shares_1) = deal(1, 2, 2).unwrap();
+        let (dealing_2, shares_2) = deal(2, 2, 2).unwrap();
+        let dealings = [dealing_1, dealing_2];
+        let shares: Vec<_> = shares_1.into_iter().chain(shares_2).collect();
+        let ours = finalize(1, 2, 2, &dealings, &shares).unwrap();
+        let peer = finalize(2, 2, 2, &dealings, &shares).unwrap();
+
+        let context = ThresholdContext::new(ours.clone(), Vec::new());
+        let ciphertext = encrypt(&ours.group_public_key.0, b"secret").unwrap();
+        let ephemeral = hex::encode(context.observe(&ciphertext).unwrap());
+
+        let (status, _) = decryption_share(Some(&context), &ephemeral);
+        assert_eq!(status, "401 Unauthorized");
+
+        let request = peer.share_request(&hex::decode(&ephemeral).unwrap());
+        let (status, body) = decryption_share(
+            Some(&context),
+            &format!("{}?{}", ephemeral, request.to_query()),
+        );
+        assert_eq!(status, "200 OK");
+        let sealed = serde_json::from_str(&body).unwrap();
+        assert!(peer.verify_share(&peer.open_share(&sealed).unwrap()).is_ok());
+    }
+}
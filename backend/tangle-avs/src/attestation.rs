@@ -0,0 +1,90 @@
+use blueprint_sdk::alloy::primitives::{address, Address, Bytes, FixedBytes};
+use blueprint_sdk::alloy::sol;
+use blueprint_sdk::alloy::sol_types::SolValue;
+use blueprint_sdk::std::sync::LazyLock;
+use k256::ecdsa::SigningKey;
+use tee_attestation::{public_key_bytes, AttestationDocument, Attester, SoftwareAttester};
+
+use crate::VerifierContext;
+
+// Operator address recorded in attestation documents
+pub static OPERATOR_ADDRESS: LazyLock<Address> = LazyLock::new(|| {
+    std::env::var("OPERATOR_ADDRESS")
+        .map(|addr| addr.parse().expect("Invalid OPERATOR_ADDRESS"))
+        .unwrap_or_else(|_| address!("0000000000000000000000000000000000000000"))
+});
+
+sol! {
+    // Layout accepted by FaceVerifier.publishAttestation
+    #[derive(Debug)]
+    struct OnchainAttestation {
+        uint16 version;
+        bytes32 measurement;
+        bytes teePublicKey;
+        address operator;
+        uint64 timestamp;
+        bytes signature;
+    }
+}
+
+// Public key users encrypt to, SEC1 compressed secp256k1
+pub fn tee_public_key(tee_private_key: &[u8; 32]) -> Vec<u8> {
+    let key = SigningKey::from_slice(tee_private_key).expect("Invalid TEE private key");
+    public_key_bytes(&key)
+}
+
+// Pick the attestation backend; only the software simulator exists so far
+pub fn load_attester() -> Result<Box<dyn Attester>, String> {
+    match std::env::var("ATTESTER_ROOT_KEY") {
+        Ok(key_str) => {
+            let bytes: [u8; 32] = hex::decode(key_str.trim_start_matches("0x"))
+                .map_err(|e| e.to_string())?
+                .try_into()
+                .map_err(|_| "ATTESTER_ROOT_KEY must be 32 bytes".to_string())?;
+            Ok(Box::new(SoftwareAttester::from_root_bytes(&bytes)?))
+        }
+        Err(_) => Ok(Box::new(SoftwareAttester::random()?)),
+    }
+}
+
+// Attest to the running binary and the key users encrypt to
+pub fn attest(
+    attester: &dyn Attester,
+    context: &VerifierContext,
+) -> Result<AttestationDocument, String> {
+    let measurement = tee_attestation::measure_current_exe().map_err(|e| e.to_string())?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs();
+
+    AttestationDocument::create(
+        attester,
+        measurement,
+        context.encryption_public_key(),
+        OPERATOR_ADDRESS.into_array(),
+        timestamp,
+    )
+}
+
+// ABI-encode a document for FaceVerifier.publishAttestation
+pub fn to_onchain(document: &AttestationDocument) -> Bytes {
+    // ecrecover expects v in {27, 28}
+    let mut signature = document.signature.clone();
+    if let Some(v) = signature.last_mut() {
+        if *v < 27 {
+            *v += 27;
+        }
+    }
+
+    OnchainAttestation {
+        version: document.version,
+        measurement: FixedBytes(document.measurement),
+        teePublicKey: document.tee_public_key.clone().into(),
+        operator: Address::from(document.operator),
+        timestamp: document.timestamp,
+        signature: signature.into(),
+    }
+    .abi_encode()
+    .into()
+}
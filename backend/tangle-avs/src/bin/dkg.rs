@@ -0,0 +1,122 @@
+// DKG ceremony for the drew-v decryption committee.
+//
+// Every operator runs `deal`, broadcasts its dealing file and sends each
+// share file privately to the operator it is addressed to. Once an operator
+// has every dealing and the shares addressed to it, `finalize` checks them
+// and writes the key share drew-v loads from THRESHOLD_KEY_SHARE. The group
+// public key it prints is what FaceVerifier.setTeePublicKey should be set to.
+
+use clap::{Parser, Subcommand};
+use drew_v::threshold::{deal, finalize, Dealing, DealtShare};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(
+    name = "drew-v-dkg",
+    about = "Distributed key generation for the decryption committee"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Round 1: generate this operator's dealing and shares
+    Deal {
+        #[arg(long)]
+        index: u32,
+        #[arg(long)]
+        threshold: u32,
+        #[arg(long)]
+        participants: u32,
+        /// Directory to write dealing-<index>.json and share-<index>-<recipient>.json to
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Round 2: verify received shares and derive this operator's key share
+    Finalize {
+        #[arg(long)]
+        index: u32,
+        #[arg(long)]
+        threshold: u32,
+        #[arg(long)]
+        participants: u32,
+        /// Directory holding every dealing and the shares addressed to this operator
+        #[arg(long)]
+        dir: PathBuf,
+        /// Where to write the key share
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+fn main() -> Result<(), String> {
+    match Cli::parse().command {
+        Command::Deal {
+            index,
+            threshold,
+            participants,
+            out,
+        } => {
+            let (dealing, shares) = deal(index, threshold, participants)?;
+            std::fs::create_dir_all(&out).map_err(|e| e.to_string())?;
+            write_json(&out.join(format!("dealing-{}.json", index)), &dealing)?;
+            for share in &shares {
+                write_json(
+                    &out.join(format!("share-{}-{}.json", index, share.recipient)),
+                    share,
+                )?;
+            }
+            println!(
+                "Wrote dealing and {} shares to {}",
+                shares.len(),
+                out.display()
+            );
+            println!(
+                "Broadcast dealing-{}.json; send each share file only to its recipient",
+                index
+            );
+        }
+        Command::Finalize {
+            index,
+            threshold,
+            participants,
+            dir,
+            out,
+        } => {
+            let mut dealings: Vec<Dealing> = Vec::new();
+            let mut shares: Vec<DealtShare> = Vec::new();
+            for dealer in 1..=participants {
+                dealings.push(read_json(&dir.join(format!("dealing-{}.json", dealer)))?);
+                shares.push(read_json(
+                    &dir.join(format!("share-{}-{}.json", dealer, index)),
+                )?);
+            }
+
+            let key_share = finalize(index, threshold, participants, &dealings, &shares)?;
+            write_json(&out, &key_share)?;
+            println!(
+                "Wrote key share for operator {} to {}",
+                index,
+                out.display()
+            );
+            println!(
+                "Committee public key: 0x{}",
+                hex::encode(&key_share.group_public_key.0)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -1,5 +1,6 @@
 use drew_v as blueprint;
 use blueprint::{FaceVerifier, FACE_VERIFIER_ADDRESS, VerifierContext};
+use blueprint::threshold::ThresholdContext;
 use blueprint_sdk::alloy::primitives::{address, Address};
 use blueprint_sdk::logging::{info, warn};
 use blueprint_sdk::macros::main;
@@ -12,18 +13,53 @@ use rand::Rng;
 #[main(env)]
 async fn main() {
     info!("Initializing Face Verification AVS...");
+    info!("Proving spends for chain {}", *blueprint::CHAIN_ID);
     
     // Generate or load TEE private key
     let tee_private_key = generate_or_load_tee_key();
     info!("TEE private key initialized");
     
+    // Join the decryption committee if a DKG key share is configured
+    let threshold = ThresholdContext::load().map_err(|e| e.to_string())?;
+    match &threshold {
+        Some(t) => info!(
+            "Committee member {} ({}-of-{} threshold decryption)",
+            t.key_share.index,
+            t.key_share.threshold,
+            t.key_share.verification_keys.len()
+        ),
+        None => warn!("No THRESHOLD_KEY_SHARE set, decrypting payloads with the single TEE key"),
+    }
+
     // Create your service context with TEE private key
     let context = VerifierContext {
         config: env.clone(),
         tee_private_key,
+        threshold: threshold.clone(),
     };
     info!("Context initialized with TEE configuration");
 
+    // Attest to this build and the TEE key, then serve the document to clients
+    let attester = blueprint::attestation::load_attester().map_err(|e| e.to_string())?;
+    let document = blueprint::attestation::attest(attester.as_ref(), &context)
+        .map_err(|e| e.to_string())?;
+    info!(
+        "Attestation produced by {} backend, measurement 0x{}",
+        document.backend,
+        hex::encode(document.measurement)
+    );
+    let api_addr = std::env::var("ATTESTATION_API_ADDR")
+        .unwrap_or_else(|_| blueprint::api::DEFAULT_API_ADDR.to_string());
+    let api_state = blueprint::api::ApiState {
+        document: std::sync::Arc::new(document),
+        threshold,
+    };
+    tokio::spawn(async move {
+        if let Err(e) = blueprint::api::serve(api_addr, api_state).await {
+            warn!("Attestation API stopped: {:?}", e);
+        }
+    });
+
     // Get the provider
     let rpc_endpoint = env.http_rpc_endpoint.clone();
     let provider = get_provider_http(&rpc_endpoint);
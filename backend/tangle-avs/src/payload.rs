@@ -0,0 +1,307 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::{
+    auth, preflight_spend, AmountMode, EncryptedData, MerkleProof, Note, NullifierData, SpendAuthorization,
+    SpendDomain, SpendNoteInput, SpendVerificationInput,
+};
+
+// The note secrets carried in encrypted_nullifier: spending key, blinding
+// factor, then the amount as a big-endian u64. The nullifier is derived from
+// them, so it can't be chosen apart from the note.
+const NOTE_SECRETS_LEN: usize = 32 + 32 + 8;
+
+// Open the payload with `decrypt` into the spend it describes on `domain`,
+// ready to prove. A payload that is malformed, or not signed by the note
+// owner, is an error rather than a panic: anyone can emit CommitmentCreated.
+pub fn open_encrypted_spend(
+    encrypted_data: &EncryptedData,
+    domain: &SpendDomain,
+    decrypt: impl Fn(&[u8]) -> Result<Vec<u8>, String>,
+) -> Result<SpendVerificationInput, String> {
+    // 1. Decrypt the data
+    let note_secrets = decrypt(&encrypted_data.encrypted_nullifier)?;
+    let authorization = decrypt(&encrypted_data.encrypted_authorization)?;
+    let index = decrypt(&encrypted_data.encrypted_index)?;
+    let merkle_path = decrypt(&encrypted_data.encrypted_merkle_path)?;
+
+    // 2. Parse the decrypted data
+    if authorization.len() != 20 + 65 {
+        return Err(format!(
+            "Authorization must be a wallet address and a 65-byte signature, got {} bytes",
+            authorization.len()
+        ));
+    }
+    let mut wallet_address = [0u8; 20];
+    wallet_address.copy_from_slice(&authorization[..20]);
+    let authorization = SpendAuthorization { signature: authorization[20..].to_vec() };
+    // The note's position in the tree; the merkle path already pins the leaf
+    // to it, so only its shape is checked
+    let _index_of_spend_note = u64::from_be_bytes(
+        index
+            .try_into()
+            .map_err(|i: Vec<u8>| format!("Index must be 8 bytes, got {}", i.len()))?,
+    );
+
+    // Parse merkle path
+    let merkle_proof: MerkleProof = bincode::deserialize(&merkle_path)
+        .map_err(|e| format!("Failed to deserialize merkle path: {}", e))?;
+
+    // 3. The root the path leads to from the note's leaf. The owner signs it
+    // and the journal commits it, so a proof over a root the contract doesn't
+    // hold is refused when it is submitted.
+    let merkle_root = merkle_proof
+        .compute_root(&encrypted_data.note_hash)
+        .ok_or_else(|| format!("Malformed merkle path for {:?}", merkle_proof.scheme))?;
+
+    // 4. Prepare the input for RISC Zero
+    let spend_note = decrypt_everything(&note_secrets, wallet_address, &encrypted_data.note_hash, domain)?;
+
+    // Reject payloads the note owner did not sign before spending cycles on a proof
+    let message_hash = auth::spend_message_hash(
+        &spend_note.wallet_address,
+        &spend_note.nullifier,
+        &merkle_root,
+        spend_note.amount_commitment.amount,
+        &encrypted_data.recipient,
+        encrypted_data.relayer_fee,
+        domain,
+    );
+    if !auth::verify_authorization(&spend_note.wallet_address, &authorization, &message_hash) {
+        return Err("Spend authorization does not match the note owner".to_string());
+    }
+
+    spend_input(
+        spend_note,
+        merkle_proof,
+        merkle_root,
+        authorization,
+        encrypted_data.recipient,
+        encrypted_data.relayer_fee,
+        domain,
+    )
+}
+
+// Open a payload encrypted to a single TEE key: a 12-byte nonce followed by
+// the AES-256-GCM ciphertext
+pub fn decrypt_data(encrypted: &[u8], private_key: &[u8; 32]) -> Result<Vec<u8>, String> {
+    if encrypted.len() < 12 {
+        return Err(format!("Ciphertext is {} bytes, shorter than its nonce", encrypted.len()));
+    }
+    let (nonce, ciphertext) = encrypted.split_at(12);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(private_key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Ciphertext could not be decrypted with the TEE key".to_string())
+}
+
+// Rebuild the spent note from its decrypted secrets, checking it is the note
+// the event names
+fn decrypt_everything(
+    note_secrets: &[u8],
+    wallet_address: [u8; 20],
+    note_hash: &[u8; 32],
+    domain: &SpendDomain,
+) -> Result<SpendNoteInput, String> {
+    if note_secrets.len() != NOTE_SECRETS_LEN {
+        return Err(format!(
+            "Note secrets must be {} bytes, got {}",
+            NOTE_SECRETS_LEN,
+            note_secrets.len()
+        ));
+    }
+    let mut spending_key = [0u8; 32];
+    spending_key.copy_from_slice(&note_secrets[..32]);
+    let mut blinding_factor = [0u8; 32];
+    blinding_factor.copy_from_slice(&note_secrets[32..64]);
+    let mut amount = [0u8; 8];
+    amount.copy_from_slice(&note_secrets[64..]);
+
+    let note = Note {
+        wallet_address,
+        amount: u64::from_be_bytes(amount),
+        nullifier_data: NullifierData { spending_key },
+        blinding_factor,
+    };
+    match note.leaf_hash() {
+        Some(leaf) if leaf == *note_hash => {}
+        Some(_) => return Err("Note secrets do not match the note hash".to_string()),
+        None => return Err("Blinding factor is not a canonical scalar".to_string()),
+    }
+    note.to_spend_note(domain)
+        .ok_or_else(|| "Blinding factor is not a canonical scalar".to_string())
+}
+
+// Build the guest input for a spend, refusing one the guest would reject
+fn spend_input(
+    spend_note: SpendNoteInput,
+    merkle_proof: MerkleProof,
+    merkle_root: [u8; 32],
+    authorization: SpendAuthorization,
+    recipient: [u8; 20],
+    relayer_fee: u64,
+    domain: &SpendDomain,
+) -> Result<SpendVerificationInput, String> {
+    // Create the input for RISC Zero, revealing the note's own amount
+    let expected_amount = spend_note.amount_commitment.amount;
+    let input = SpendVerificationInput {
+        spend_note,
+        merkle_proof,
+        merkle_root,
+        amount_mode: AmountMode::Reveal { expected_amount },
+        authorization,
+        recipient,
+        relayer_fee,
+        domain: *domain,
+    };
+
+    // A spend the guest would reject is not worth proving
+    let status = preflight_spend(&input);
+    if !status.is_valid() {
+        return Err(format!("Spend rejected by preflight: {:?}", status));
+    }
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::SigningKey;
+    use privacy_zkp_core::MerkleTree;
+
+    use super::*;
+    use crate::MerkleScheme;
+
+    const KEY: [u8; 32] = [5u8; 32];
+
+    // Encrypt `plaintext` to KEY the way users encrypt to a single TEE
+    fn encrypt(plaintext: &[u8]) -> Vec<u8> {
+        let nonce = [9u8; 12];
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&KEY));
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(cipher.encrypt(Nonce::from_slice(&nonce), plaintext).unwrap());
+        encrypted
+    }
+
+    fn domain() -> SpendDomain {
+        SpendDomain { chain_id: 11155111, verifier_address: [3u8; 20] }
+    }
+
+    #[test]
+    fn test_decrypt_data() {
+        assert_eq!(decrypt_data(&encrypt(b"nullifier"), &KEY).unwrap(), b"nullifier");
+
+        // Too short to hold a nonce, or sealed to another key
+        assert!(decrypt_data(&[0u8; 11], &KEY).is_err());
+        assert!(decrypt_data(&encrypt(b"nullifier"), &[6u8; 32]).is_err());
+    }
+
+    // Sign `message` with personal_sign, as a browser wallet would
+    fn sign(key: &SigningKey, message: &[u8; 32]) -> Vec<u8> {
+        let digest = auth::eth_signed_message_hash(message);
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+        signature
+    }
+
+    // A spend of a 1000 wei note, signed by its owner, as its user would
+    // encrypt it; `edit` changes the plaintexts before they are encrypted
+    fn payload(edit: impl FnOnce(&mut Vec<u8>, &mut Vec<u8>, &mut Vec<u8>, &mut Vec<u8>)) -> EncryptedData {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        // The owner's address is whatever their signatures recover to
+        let digest = auth::eth_signed_message_hash(&[1u8; 32]);
+        let wallet_address = auth::recover_address(&digest, &sign(&key, &[1u8; 32])).unwrap();
+        let note = Note {
+            wallet_address,
+            amount: 1000,
+            nullifier_data: NullifierData { spending_key: [8u8; 32] },
+            blinding_factor: [2u8; 32],
+        };
+        let leaf = note.leaf_hash().unwrap();
+        let tree = MerkleTree::new(MerkleScheme::SortedKeccak256, vec![[6u8; 32], leaf, [5u8; 32]]);
+        let spend_note = note.to_spend_note(&domain()).unwrap();
+        let message = auth::spend_message_hash(
+            &wallet_address,
+            &spend_note.nullifier,
+            &tree.root(),
+            1000,
+            &[9u8; 20],
+            10,
+            &domain(),
+        );
+
+        let mut secrets = [note.nullifier_data.spending_key, note.blinding_factor].concat();
+        secrets.extend(note.amount.to_be_bytes());
+        let mut authorization = [&wallet_address[..], &sign(&key, &message)].concat();
+        let mut index = 1u64.to_be_bytes().to_vec();
+        let mut merkle_path = bincode::serialize(&tree.proof(1).unwrap()).unwrap();
+        edit(&mut secrets, &mut authorization, &mut index, &mut merkle_path);
+
+        EncryptedData {
+            encrypted_nullifier: encrypt(&secrets),
+            encrypted_authorization: encrypt(&authorization),
+            encrypted_index: encrypt(&index),
+            encrypted_merkle_path: encrypt(&merkle_path),
+            note_hash: leaf,
+            recipient: [9u8; 20],
+            relayer_fee: 10,
+        }
+    }
+
+    fn open(payload: &EncryptedData) -> Result<SpendVerificationInput, String> {
+        open_encrypted_spend(payload, &domain(), |ciphertext| decrypt_data(ciphertext, &KEY))
+    }
+
+    #[test]
+    fn test_signed_payloads_open_into_provable_spends() {
+        let payload = payload(|_, _, _, _| {});
+        let input = open(&payload).unwrap();
+        assert_eq!(input.amount_mode, AmountMode::Reveal { expected_amount: 1000 });
+        assert_eq!(input.recipient, [9u8; 20]);
+        assert_eq!(input.domain, domain());
+        assert!(input.merkle_proof.verify(&payload.note_hash, &input.merkle_root));
+        assert!(preflight_spend(&input).is_valid());
+    }
+
+    #[test]
+    fn test_malformed_payloads_are_rejected() {
+        let error = open(&payload(|secrets, _, _, _| secrets.truncate(71))).unwrap_err();
+        assert_eq!(error, "Note secrets must be 72 bytes, got 71");
+
+        let error = open(&payload(|_, authorization, _, _| authorization.truncate(84))).unwrap_err();
+        assert!(error.starts_with("Authorization must be"), "{}", error);
+
+        let error = open(&payload(|_, _, index, _| index.truncate(4))).unwrap_err();
+        assert_eq!(error, "Index must be 8 bytes, got 4");
+
+        let error = open(&payload(|_, _, _, merkle_path| merkle_path.clear())).unwrap_err();
+        assert!(error.starts_with("Failed to deserialize merkle path"), "{}", error);
+
+        // An ordered path needs a side for every sibling
+        let proof = MerkleProof { scheme: MerkleScheme::OrderedSha256, path: vec![[0u8; 32]; 2], indices: vec![true] };
+        let unsided = bincode::serialize(&proof).unwrap();
+        let error = open(&payload(|_, _, _, merkle_path| *merkle_path = unsided)).unwrap_err();
+        assert_eq!(error, "Malformed merkle path for OrderedSha256");
+
+        let mut undecryptable = payload(|_, _, _, _| {});
+        undecryptable.encrypted_authorization[20] ^= 1;
+        assert!(open(&undecryptable).is_err());
+    }
+
+    #[test]
+    fn test_payloads_must_match_their_note_and_signature() {
+        // Secrets of another note than the event's
+        let error = open(&payload(|secrets, _, _, _| secrets[0] ^= 1)).unwrap_err();
+        assert_eq!(error, "Note secrets do not match the note hash");
+
+        // A claimed amount the note wasn't deposited with
+        let error = open(&payload(|secrets, _, _, _| secrets[71] ^= 1)).unwrap_err();
+        assert_eq!(error, "Note secrets do not match the note hash");
+
+        // Signed over another fee than the event's
+        let mut refeed = payload(|_, _, _, _| {});
+        refeed.relayer_fee = 11;
+        assert_eq!(open(&refeed).unwrap_err(), "Spend authorization does not match the note owner");
+    }
+}
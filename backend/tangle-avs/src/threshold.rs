@@ -0,0 +1,759 @@
+// Threshold ElGamal over secp256k1 for commitment payloads.
+//
+// Payloads are encrypted to a committee key Y = x*G whose secret x is
+// Shamir-shared between operators by a Feldman DKG. A payload carries an
+// ephemeral point R; each operator publishes x_i*R with a DLEQ proof and any
+// `threshold` valid shares recombine to x*R, which keys AES-256-GCM. No
+// operator ever holds x itself.
+//
+// Shares are only handed to other committee members. A request is signed
+// with the requester's key share x_j, checked against its verification key
+// x_j*G from the DKG, and the share is sealed to that same key, so nobody
+// outside the committee can gather enough shares to open a payload, even
+// for an ephemeral they re-emit in their own CommitmentCreated.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use k256::elliptic_curve::group::GroupEncoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::{Field, PrimeField};
+use k256::{CompressedPoint, ProjectivePoint, Scalar, U256};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use blueprint_sdk::logging::warn;
+
+const PAYLOAD_DOMAIN: &[u8] = b"IntelliFi threshold payload";
+const DLEQ_DOMAIN: &[u8] = b"IntelliFi decryption share";
+const REQUEST_DOMAIN: &[u8] = b"IntelliFi decryption share request";
+const SEALED_SHARE_DOMAIN: &[u8] = b"IntelliFi sealed decryption share";
+const POINT_LEN: usize = 33;
+const NONCE_LEN: usize = 12;
+
+// Compressed curve point, hex in JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PointBytes(#[serde(with = "hex::serde")] pub Vec<u8>);
+
+// One operator's share of the committee secret, produced by `finalize`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyShare {
+    pub index: u32,
+    pub threshold: u32,
+    #[serde(with = "hex::serde")]
+    secret: [u8; 32],
+    pub group_public_key: PointBytes,
+    pub verification_keys: Vec<PointBytes>, // x_j*G for participant j at position j - 1
+}
+
+// Public half of a dealer's polynomial, broadcast to everyone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dealing {
+    pub dealer: u32,
+    pub threshold: u32,
+    pub commitments: Vec<PointBytes>,
+}
+
+// f_dealer(recipient), sent privately to the recipient
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DealtShare {
+    pub dealer: u32,
+    pub recipient: u32,
+    #[serde(with = "hex::serde")]
+    pub value: [u8; 32],
+}
+
+// x_i*R for one ciphertext's ephemeral point, with a proof it used x_i
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecryptionShare {
+    pub index: u32,
+    pub ephemeral: PointBytes,
+    pub share: PointBytes,
+    #[serde(with = "hex::serde")]
+    pub challenge: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub response: [u8; 32],
+}
+
+// A committee member asking for our share of `ephemeral`: a Schnorr signature
+// by participant `requester` over the ephemeral, as (commitment, response)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareRequest {
+    pub requester: u32,
+    pub commitment: PointBytes,
+    #[serde(with = "hex::serde")]
+    pub response: [u8; 32],
+}
+
+// A DecryptionShare encrypted to the requester's verification key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedShare {
+    #[serde(with = "hex::serde")]
+    pub ciphertext: Vec<u8>,
+}
+
+// Why a share request was not answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareError {
+    // The request was not signed by a committee member
+    Unauthorized(String),
+    // The ephemeral never appeared on-chain, or not yet
+    NotObserved,
+    Invalid(String),
+}
+
+impl std::fmt::Display for ShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShareError::Unauthorized(e) => write!(f, "unauthorized: {}", e),
+            ShareError::NotObserved => write!(f, "payload not observed"),
+            ShareError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn encode_point(point: &ProjectivePoint) -> PointBytes {
+    PointBytes(point.to_affine().to_bytes().to_vec())
+}
+
+fn decode_point(bytes: &[u8]) -> Result<ProjectivePoint, String> {
+    if bytes.len() != POINT_LEN {
+        return Err(format!("Invalid point length: {}", bytes.len()));
+    }
+    Option::from(ProjectivePoint::from_bytes(CompressedPoint::from_slice(
+        bytes,
+    )))
+    .ok_or_else(|| "Invalid curve point".to_string())
+}
+
+fn decode_scalar(bytes: &[u8; 32]) -> Result<Scalar, String> {
+    Option::from(Scalar::from_repr((*bytes).into())).ok_or_else(|| "Invalid scalar".to_string())
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    <Scalar as Reduce<U256>>::reduce_bytes(&hasher.finalize())
+}
+
+// sum_k index^k * C_k, the public image of a polynomial at `index`
+fn eval_commitments(commitments: &[ProjectivePoint], index: u32) -> ProjectivePoint {
+    let x = Scalar::from(index as u64);
+    commitments
+        .iter()
+        .rev()
+        .fold(ProjectivePoint::IDENTITY, |acc, c| acc * x + c)
+}
+
+// Lagrange coefficient for `index` at zero over `indices`
+fn lagrange_at_zero(index: u32, indices: &[u32]) -> Scalar {
+    let xi = Scalar::from(index as u64);
+    indices
+        .iter()
+        .filter(|&&j| j != index)
+        .fold(Scalar::ONE, |acc, &j| {
+            let xj = Scalar::from(j as u64);
+            acc * xj * (xj - xi).invert().unwrap()
+        })
+}
+
+// Round 1: pick a random polynomial of degree threshold - 1 and share it
+pub fn deal(
+    dealer: u32,
+    threshold: u32,
+    participants: u32,
+) -> Result<(Dealing, Vec<DealtShare>), String> {
+    if threshold == 0 || threshold > participants || dealer == 0 || dealer > participants {
+        return Err("Invalid committee parameters".to_string());
+    }
+
+    let mut rng = rand::thread_rng();
+    let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(&mut rng)).collect();
+
+    let commitments = coefficients
+        .iter()
+        .map(|a| encode_point(&(ProjectivePoint::GENERATOR * a)))
+        .collect();
+
+    let shares = (1..=participants)
+        .map(|recipient| {
+            let x = Scalar::from(recipient as u64);
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::ZERO, |acc, a| acc * x + a);
+            DealtShare {
+                dealer,
+                recipient,
+                value: value.to_bytes().into(),
+            }
+        })
+        .collect();
+
+    Ok((
+        Dealing {
+            dealer,
+            threshold,
+            commitments,
+        },
+        shares,
+    ))
+}
+
+// Check a received share against the dealer's broadcast commitments
+pub fn verify_dealt_share(dealing: &Dealing, share: &DealtShare) -> Result<(), String> {
+    if dealing.dealer != share.dealer {
+        return Err("Share and dealing are from different dealers".to_string());
+    }
+    if dealing.commitments.len() != dealing.threshold as usize {
+        return Err(format!(
+            "Dealer {} committed to the wrong degree",
+            dealing.dealer
+        ));
+    }
+
+    let commitments = dealing
+        .commitments
+        .iter()
+        .map(|c| decode_point(&c.0))
+        .collect::<Result<Vec<_>, _>>()?;
+    let value = decode_scalar(&share.value)?;
+
+    if ProjectivePoint::GENERATOR * value != eval_commitments(&commitments, share.recipient) {
+        return Err(format!(
+            "Share from dealer {} does not match its commitments",
+            share.dealer
+        ));
+    }
+    Ok(())
+}
+
+// Round 2: sum the shares addressed to `index` from every dealer
+pub fn finalize(
+    index: u32,
+    threshold: u32,
+    participants: u32,
+    dealings: &[Dealing],
+    shares: &[DealtShare],
+) -> Result<KeyShare, String> {
+    let mut dealers: Vec<u32> = dealings.iter().map(|d| d.dealer).collect();
+    dealers.sort_unstable();
+    dealers.dedup();
+    if dealings.len() != participants as usize || dealers != (1..=participants).collect::<Vec<_>>()
+    {
+        return Err("Need exactly one dealing from every participant".to_string());
+    }
+    if dealings.iter().any(|d| d.threshold != threshold) {
+        return Err("Dealings disagree on the threshold".to_string());
+    }
+
+    let mut secret = Scalar::ZERO;
+    for dealing in dealings {
+        let share = shares
+            .iter()
+            .find(|s| s.dealer == dealing.dealer && s.recipient == index)
+            .ok_or_else(|| format!("Missing share from dealer {}", dealing.dealer))?;
+        verify_dealt_share(dealing, share)?;
+        secret += decode_scalar(&share.value)?;
+    }
+
+    // The joint polynomial's commitments are the sums of every dealer's
+    let mut joint = vec![ProjectivePoint::IDENTITY; threshold as usize];
+    for dealing in dealings {
+        for (k, commitment) in dealing.commitments.iter().enumerate() {
+            joint[k] += decode_point(&commitment.0)?;
+        }
+    }
+
+    Ok(KeyShare {
+        index,
+        threshold,
+        secret: secret.to_bytes().into(),
+        group_public_key: encode_point(&joint[0]),
+        verification_keys: (1..=participants)
+            .map(|j| encode_point(&eval_commitments(&joint, j)))
+            .collect(),
+    })
+}
+
+// Ephemeral point a payload was encrypted under
+pub fn ephemeral_of(ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    if ciphertext.len() < POINT_LEN + NONCE_LEN {
+        return Err("Threshold ciphertext too short".to_string());
+    }
+    decode_point(&ciphertext[..POINT_LEN])?;
+    Ok(ciphertext[..POINT_LEN].to_vec())
+}
+
+fn payload_key(domain: &[u8], shared: &ProjectivePoint) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(domain);
+    hasher.update(encode_point(shared).0);
+    hasher.finalize().into()
+}
+
+// R || nonce || AES-256-GCM(H(domain, r*Y), plaintext)
+fn seal(domain: &[u8], public_key: &ProjectivePoint, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut rng = rand::thread_rng();
+    let r = Scalar::random(&mut rng);
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+
+    let key = payload_key(domain, &(*public_key * r));
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| e.to_string())?;
+
+    let mut out = encode_point(&(ProjectivePoint::GENERATOR * r)).0;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+// Open what `seal` produced, given y*R for the recipient's secret y
+fn open(domain: &[u8], shared: &ProjectivePoint, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let key = payload_key(domain, shared);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Nonce::from_slice(&ciphertext[POINT_LEN..POINT_LEN + NONCE_LEN]);
+    cipher
+        .decrypt(nonce, &ciphertext[POINT_LEN + NONCE_LEN..])
+        .map_err(|e| format!("Threshold decryption failed: {:?}", e))
+}
+
+// Client side: encrypt a payload to the committee key
+pub fn encrypt(group_public_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    seal(PAYLOAD_DOMAIN, &decode_point(group_public_key)?, plaintext)
+}
+
+impl KeyShare {
+    fn secret(&self) -> Scalar {
+        decode_scalar(&self.secret).expect("Key share holds a valid scalar")
+    }
+
+    fn verification_key(&self, index: u32) -> Result<ProjectivePoint, String> {
+        let key = self
+            .verification_keys
+            .get((index as usize).wrapping_sub(1))
+            .ok_or_else(|| format!("Unknown participant {}", index))?;
+        decode_point(&key.0)
+    }
+
+    // Sign a request for a peer's share of `ephemeral`
+    pub fn share_request(&self, ephemeral: &[u8]) -> ShareRequest {
+        let x = self.secret();
+        let k = Scalar::random(&mut rand::thread_rng());
+        let commitment = ProjectivePoint::GENERATOR * k;
+        let challenge = request_challenge(
+            &(ProjectivePoint::GENERATOR * x),
+            &commitment,
+            self.index,
+            ephemeral,
+        );
+        ShareRequest {
+            requester: self.index,
+            commitment: encode_point(&commitment),
+            response: (k + challenge * x).to_bytes().into(),
+        }
+    }
+
+    // Check a request comes from the committee member it names
+    pub fn verify_request(&self, ephemeral: &[u8], request: &ShareRequest) -> Result<(), String> {
+        let verification_key = self.verification_key(request.requester)?;
+        let commitment = decode_point(&request.commitment.0)?;
+        let response = decode_scalar(&request.response)?;
+        let challenge = request_challenge(&verification_key, &commitment, request.requester, ephemeral);
+        if ProjectivePoint::GENERATOR * response != commitment + verification_key * challenge {
+            return Err(format!("Invalid signature for participant {}", request.requester));
+        }
+        Ok(())
+    }
+
+    // Our share of `ephemeral`, readable only by the requester
+    pub fn sealed_share(&self, ephemeral: &[u8], requester: u32) -> Result<SealedShare, String> {
+        let share = self.decryption_share(ephemeral)?;
+        let plaintext = serde_json::to_vec(&share).map_err(|e| e.to_string())?;
+        let ciphertext = seal(SEALED_SHARE_DOMAIN, &self.verification_key(requester)?, &plaintext)?;
+        Ok(SealedShare { ciphertext })
+    }
+
+    // Open a share a peer sealed to us
+    pub fn open_share(&self, sealed: &SealedShare) -> Result<DecryptionShare, String> {
+        let ephemeral = decode_point(&ephemeral_of(&sealed.ciphertext)?)?;
+        let plaintext = open(SEALED_SHARE_DOMAIN, &(ephemeral * self.secret()), &sealed.ciphertext)?;
+        serde_json::from_slice(&plaintext).map_err(|e| e.to_string())
+    }
+
+    // This operator's contribution towards decrypting payloads under `ephemeral`
+    pub fn decryption_share(&self, ephemeral: &[u8]) -> Result<DecryptionShare, String> {
+        let r = decode_point(ephemeral)?;
+        let x = self.secret();
+        let share = r * x;
+
+        // Chaum-Pedersen proof that log_G(x*G) == log_R(x*R)
+        let k = Scalar::random(&mut rand::thread_rng());
+        let verification_key = ProjectivePoint::GENERATOR * x;
+        let challenge = dleq_challenge(
+            &verification_key,
+            &r,
+            &share,
+            &(ProjectivePoint::GENERATOR * k),
+            &(r * k),
+        );
+        let response = k + challenge * x;
+
+        Ok(DecryptionShare {
+            index: self.index,
+            ephemeral: PointBytes(ephemeral.to_vec()),
+            share: encode_point(&share),
+            challenge: challenge.to_bytes().into(),
+            response: response.to_bytes().into(),
+        })
+    }
+
+    // Check a peer's share against its verification key from the DKG
+    pub fn verify_share(&self, share: &DecryptionShare) -> Result<(), String> {
+        let verification_key = self.verification_key(share.index)?;
+        let r = decode_point(&share.ephemeral.0)?;
+        let d = decode_point(&share.share.0)?;
+        let c = decode_scalar(&share.challenge)?;
+        let z = decode_scalar(&share.response)?;
+
+        let a1 = ProjectivePoint::GENERATOR * z - verification_key * c;
+        let a2 = r * z - d * c;
+        if dleq_challenge(&verification_key, &r, &d, &a1, &a2) != c {
+            return Err(format!(
+                "Invalid decryption share from participant {}",
+                share.index
+            ));
+        }
+        Ok(())
+    }
+
+    // Recombine `threshold` valid shares and open the payload
+    pub fn decrypt(
+        &self,
+        ciphertext: &[u8],
+        shares: &[DecryptionShare],
+    ) -> Result<Vec<u8>, String> {
+        let ephemeral = ephemeral_of(ciphertext)?;
+
+        let mut valid: Vec<&DecryptionShare> = Vec::new();
+        for share in shares.iter().filter(|s| s.ephemeral.0 == ephemeral) {
+            if valid.iter().any(|v| v.index == share.index) || self.verify_share(share).is_err() {
+                continue;
+            }
+            valid.push(share);
+            if valid.len() == self.threshold as usize {
+                break;
+            }
+        }
+        if valid.len() < self.threshold as usize {
+            return Err(format!(
+                "Need {} valid decryption shares, have {}",
+                self.threshold,
+                valid.len()
+            ));
+        }
+
+        let indices: Vec<u32> = valid.iter().map(|s| s.index).collect();
+        let mut shared = ProjectivePoint::IDENTITY;
+        for share in &valid {
+            shared += decode_point(&share.share.0)? * lagrange_at_zero(share.index, &indices);
+        }
+
+        open(PAYLOAD_DOMAIN, &shared, ciphertext)
+    }
+}
+
+fn request_challenge(
+    verification_key: &ProjectivePoint,
+    commitment: &ProjectivePoint,
+    requester: u32,
+    ephemeral: &[u8],
+) -> Scalar {
+    hash_to_scalar(&[
+        REQUEST_DOMAIN,
+        &encode_point(verification_key).0,
+        &encode_point(commitment).0,
+        &requester.to_be_bytes(),
+        ephemeral,
+    ])
+}
+
+fn dleq_challenge(
+    verification_key: &ProjectivePoint,
+    r: &ProjectivePoint,
+    share: &ProjectivePoint,
+    a1: &ProjectivePoint,
+    a2: &ProjectivePoint,
+) -> Scalar {
+    hash_to_scalar(&[
+        DLEQ_DOMAIN,
+        &encode_point(verification_key).0,
+        &encode_point(r).0,
+        &encode_point(share).0,
+        &encode_point(a1).0,
+        &encode_point(a2).0,
+    ])
+}
+
+impl ShareRequest {
+    // requester=<index>&commitment=<hex>&response=<hex>
+    pub fn to_query(&self) -> String {
+        format!(
+            "requester={}&commitment={}&response={}",
+            self.requester,
+            hex::encode(&self.commitment.0),
+            hex::encode(self.response)
+        )
+    }
+
+    pub fn from_query(query: &str) -> Result<Self, String> {
+        let mut requester = None;
+        let mut commitment = None;
+        let mut response = None;
+        for pair in query.split('&') {
+            match pair.split_once('=') {
+                Some(("requester", value)) => requester = value.parse::<u32>().ok(),
+                Some(("commitment", value)) => commitment = hex::decode(value).ok(),
+                Some(("response", value)) => {
+                    response = hex::decode(value).ok().and_then(|r| r.try_into().ok())
+                }
+                _ => {}
+            }
+        }
+        match (requester, commitment, response) {
+            (Some(requester), Some(commitment), Some(response)) => Ok(ShareRequest {
+                requester,
+                commitment: PointBytes(commitment),
+                response,
+            }),
+            _ => Err("Malformed share request".to_string()),
+        }
+    }
+}
+
+// This operator's seat on the decryption committee
+#[derive(Clone)]
+pub struct ThresholdContext {
+    pub key_share: KeyShare,
+    pub peers: Vec<String>, // API base URLs of the other operators
+    observed: Arc<Mutex<HashSet<Vec<u8>>>>,
+}
+
+impl ThresholdContext {
+    pub fn new(key_share: KeyShare, peers: Vec<String>) -> Self {
+        Self {
+            key_share,
+            peers,
+            observed: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    // Load from THRESHOLD_KEY_SHARE (path to finalize output) and COMMITTEE_PEERS
+    pub fn load() -> Result<Option<Self>, String> {
+        let Ok(path) = std::env::var("THRESHOLD_KEY_SHARE") else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let key_share: KeyShare = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        let peers = std::env::var("COMMITTEE_PEERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|peer| peer.trim().trim_end_matches('/').to_string())
+            .filter(|peer| !peer.is_empty())
+            .collect();
+        Ok(Some(Self::new(key_share, peers)))
+    }
+
+    // Remember an ephemeral seen in a CommitmentCreated event. Shares are only
+    // served for these, so the share endpoint is not a decryption oracle.
+    pub fn observe(&self, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        let ephemeral = ephemeral_of(ciphertext)?;
+        self.observed.lock().unwrap().insert(ephemeral.clone());
+        Ok(ephemeral)
+    }
+
+    // Our share for the committee member that signed `request`, sealed to it
+    pub fn share_for(&self, ephemeral: &[u8], request: Option<&ShareRequest>) -> Result<SealedShare, ShareError> {
+        let request = request.ok_or_else(|| ShareError::Unauthorized("missing signature".to_string()))?;
+        self.key_share
+            .verify_request(ephemeral, request)
+            .map_err(ShareError::Unauthorized)?;
+        if !self.observed.lock().unwrap().contains(ephemeral) {
+            return Err(ShareError::NotObserved);
+        }
+        self.key_share
+            .sealed_share(ephemeral, request.requester)
+            .map_err(ShareError::Invalid)
+    }
+
+    // Gather our own share plus enough valid peer shares for `ephemeral`
+    pub async fn collect_shares(&self, ephemeral: &[u8]) -> Result<Vec<DecryptionShare>, String> {
+        let mut shares = vec![self.key_share.decryption_share(ephemeral)?];
+        let client = reqwest::Client::new();
+
+        // Peers may not have processed the event yet, so retry a few times
+        for attempt in 0..5 {
+            for peer in &self.peers {
+                if shares.len() >= self.key_share.threshold as usize {
+                    return Ok(shares);
+                }
+                let url = format!(
+                    "{}/decryption-share/{}?{}",
+                    peer,
+                    hex::encode(ephemeral),
+                    self.key_share.share_request(ephemeral).to_query()
+                );
+                let share = match client.get(&url).send().await {
+                    Ok(response) if response.status().is_success() => response
+                        .json::<SealedShare>()
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|sealed| self.key_share.open_share(&sealed)),
+                    Ok(response) => Err(format!("status {}", response.status())),
+                    Err(e) => Err(e.to_string()),
+                };
+                match share.and_then(|s| self.key_share.verify_share(&s).map(|_| s)) {
+                    Ok(share) if shares.iter().all(|s| s.index != share.index) => {
+                        shares.push(share)
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(
+                        "No decryption share from {} (attempt {}): {}",
+                        peer,
+                        attempt + 1,
+                        e
+                    ),
+                }
+            }
+            if shares.len() >= self.key_share.threshold as usize {
+                return Ok(shares);
+            }
+            blueprint_sdk::tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+
+        Err(format!(
+            "Collected {} of {} decryption shares",
+            shares.len(),
+            self.key_share.threshold
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn committee(threshold: u32, participants: u32) -> Vec<KeyShare> {
+        let mut dealings = Vec::new();
+        let mut shares = Vec::new();
+        for dealer in 1..=participants {
+            let (dealing, dealt) = deal(dealer, threshold, participants).unwrap();
+            dealings.push(dealing);
+            shares.extend(dealt);
+        }
+        (1..=participants)
+            .map(|i| finalize(i, threshold, participants, &dealings, &shares).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_threshold_round_trip() {
+        let keys = committee(2, 3);
+        assert!(keys
+            .iter()
+            .all(|k| k.group_public_key == keys[0].group_public_key));
+
+        let ciphertext = encrypt(&keys[0].group_public_key.0, b"merkle path").unwrap();
+        let ephemeral = ephemeral_of(&ciphertext).unwrap();
+
+        // Any two of three operators can open it
+        let shares: Vec<_> = [&keys[0], &keys[2]]
+            .iter()
+            .map(|k| k.decryption_share(&ephemeral).unwrap())
+            .collect();
+        assert_eq!(
+            keys[1].decrypt(&ciphertext, &shares).unwrap(),
+            b"merkle path"
+        );
+
+        // One is not enough
+        assert!(keys[1].decrypt(&ciphertext, &shares[..1]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_forged_share() {
+        let keys = committee(2, 3);
+        let ciphertext = encrypt(&keys[0].group_public_key.0, b"secret").unwrap();
+        let ephemeral = ephemeral_of(&ciphertext).unwrap();
+
+        let mut forged = keys[0].decryption_share(&ephemeral).unwrap();
+        forged.share = keys[1].decryption_share(&ephemeral).unwrap().share;
+        assert!(keys[2].verify_share(&forged).is_err());
+    }
+
+    #[test]
+    fn test_shares_only_go_to_committee_members() {
+        let keys = committee(2, 3);
+        let outsiders = committee(2, 3);
+        let context = ThresholdContext::new(keys[0].clone(), Vec::new());
+        let ciphertext = encrypt(&keys[0].group_public_key.0, b"secret").unwrap();
+        let ephemeral = context.observe(&ciphertext).unwrap();
+
+        // Unsigned, or signed by a key that isn't the named member's
+        assert!(matches!(
+            context.share_for(&ephemeral, None),
+            Err(ShareError::Unauthorized(_))
+        ));
+        let mut impostor = outsiders[1].share_request(&ephemeral);
+        assert!(matches!(
+            context.share_for(&ephemeral, Some(&impostor)),
+            Err(ShareError::Unauthorized(_))
+        ));
+        impostor.requester = 3;
+        assert!(matches!(
+            context.share_for(&ephemeral, Some(&impostor)),
+            Err(ShareError::Unauthorized(_))
+        ));
+
+        // A signature for one ephemeral doesn't cover another
+        let other = context
+            .observe(&encrypt(&keys[0].group_public_key.0, b"other").unwrap())
+            .unwrap();
+        assert!(matches!(
+            context.share_for(&other, Some(&keys[1].share_request(&ephemeral))),
+            Err(ShareError::Unauthorized(_))
+        ));
+
+        // A member gets a share only it can open, through the query encoding
+        let request = ShareRequest::from_query(&keys[1].share_request(&ephemeral).to_query()).unwrap();
+        let sealed = context.share_for(&ephemeral, Some(&request)).unwrap();
+        assert!(keys[2].open_share(&sealed).is_err());
+        let share = keys[1].open_share(&sealed).unwrap();
+        assert!(keys[1].verify_share(&share).is_ok());
+        let own = keys[1].decryption_share(&ephemeral).unwrap();
+        assert_eq!(keys[1].decrypt(&ciphertext, &[share, own]).unwrap(), b"secret");
+
+        // Members still only get shares for payloads seen on-chain
+        let unseen = ephemeral_of(&encrypt(&keys[0].group_public_key.0, b"x").unwrap()).unwrap();
+        assert_eq!(
+            context.share_for(&unseen, Some(&keys[1].share_request(&unseen))).unwrap_err(),
+            ShareError::NotObserved
+        );
+    }
+
+    #[test]
+    fn test_rejects_tampered_dealing() {
+        let (dealing, mut shares) = deal(1, 2, 3).unwrap();
+        shares[0].value = [1u8; 32];
+        assert!(verify_dealt_share(&dealing, &shares[0]).is_err());
+        assert!(verify_dealt_share(&dealing, &shares[1]).is_ok());
+    }
+}
@@ -16,6 +16,7 @@ fn test_verifier_context_creation() {
     let context = VerifierContext {
         config: GadgetConfiguration::default(),
         tee_private_key: [0u8; 32],
+        threshold: None,
     };
     
     // Verify context properties
@@ -27,12 +28,12 @@ fn test_encrypted_data_creation() {
     // Create encrypted data
     let data = EncryptedData {
         encrypted_nullifier: vec![1, 2, 3],
-        encrypted_private_key: vec![4, 5, 6],
+        encrypted_authorization: vec![4, 5, 6],
     };
     
     // Verify data properties
     assert_eq!(data.encrypted_nullifier, vec![1, 2, 3]);
-    assert_eq!(data.encrypted_private_key, vec![4, 5, 6]);
+    assert_eq!(data.encrypted_authorization, vec![4, 5, 6]);
 }
 
 #[test]
@@ -28,6 +28,7 @@ async fn test_commitment_event_listener() {
     let context = VerifierContext {
         config: GadgetConfiguration::default(),
         tee_private_key: [0u8; 32],
+        threshold: None,
     };
 
     // Create event handler
@@ -105,6 +106,7 @@ async fn test_event_listener_recovery() {
     let context = VerifierContext {
         config: GadgetConfiguration::default(),
         tee_private_key: [0u8; 32],
+        threshold: None,
     };
 
     // Create event handler
@@ -0,0 +1,108 @@
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+
+use crate::verify::{verify_document, AttestationPolicy, Verdict};
+use crate::AttestationDocument;
+
+// Fetch the document served by drew-v at GET /attestation
+pub async fn fetch_document(avs_url: &str) -> Result<AttestationDocument, String> {
+    let url = format!("{}/attestation", avs_url.trim_end_matches('/'));
+    reqwest::get(&url)
+        .await
+        .map_err(|e| e.to_string())?
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Read FaceVerifier.teePublicKey() with a raw eth_call
+pub async fn fetch_tee_public_key(rpc_url: &str, verifier: [u8; 20]) -> Result<Vec<u8>, String> {
+    let selector = &Keccak256::digest(b"teePublicKey()")[..4];
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_call",
+        "params": [
+            { "to": format!("0x{}", hex::encode(verifier)), "data": format!("0x{}", hex::encode(selector)) },
+            "latest"
+        ],
+    });
+
+    let response: Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(error) = response.get("error") {
+        return Err(format!("eth_call failed: {}", error));
+    }
+    let result = response["result"]
+        .as_str()
+        .ok_or("Missing eth_call result")?;
+    let data = hex::decode(result.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    decode_abi_bytes(&data)
+}
+
+// Fetch both sides and check them against the policy
+pub async fn verify_remote(
+    avs_url: &str,
+    rpc_url: &str,
+    verifier: [u8; 20],
+    policy: &AttestationPolicy,
+) -> Result<Verdict, String> {
+    let document = fetch_document(avs_url).await?;
+    let expected_key = fetch_tee_public_key(rpc_url, verifier).await?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+
+    Ok(verify_document(&document, policy, &expected_key, now))
+}
+
+// Decode a single ABI `bytes` return value
+fn decode_abi_bytes(data: &[u8]) -> Result<Vec<u8>, String> {
+    let word = |at: usize| -> Result<usize, String> {
+        let slot = at
+            .checked_add(32)
+            .and_then(|end| data.get(at..end))
+            .ok_or("ABI data too short")?;
+        // Offsets and lengths fit in the low 8 bytes of the word
+        Ok(u64::from_be_bytes(slot[24..].try_into().unwrap()) as usize)
+    };
+
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32).ok_or("ABI offset overflow")?;
+    let end = start.checked_add(len).ok_or("ABI length overflow")?;
+    data.get(start..end)
+        .map(|bytes| bytes.to_vec())
+        .ok_or_else(|| "ABI bytes out of range".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_abi_bytes() {
+        let mut data = vec![0u8; 32];
+        data[31] = 0x20;
+        let mut len = [0u8; 32];
+        len[31] = 3;
+        data.extend_from_slice(&len);
+        let mut value = [0u8; 32];
+        value[..3].copy_from_slice(&[0xaa, 0xbb, 0xcc]);
+        data.extend_from_slice(&value);
+
+        assert_eq!(decode_abi_bytes(&data).unwrap(), vec![0xaa, 0xbb, 0xcc]);
+        assert!(decode_abi_bytes(&data[..40]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::Attester;
+
+pub const ATTESTATION_VERSION: u16 = 1;
+
+// Domain tags. The report digest is laid out like abi.encodePacked so a
+// contract could check it, but FaceVerifier.publishAttestation does not: it
+// stores the document as published, and clients verify the signature chain
+// off-chain with `verify` before trusting teePublicKey.
+const REPORT_DOMAIN: &[u8] = b"IntelliFi TEE attestation";
+const ENDORSEMENT_DOMAIN: &[u8] = b"IntelliFi TEE endorsement";
+
+/// Signed statement that `tee_public_key` belongs to a TEE running the binary
+/// with `measurement` on behalf of `operator`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationDocument {
+    pub version: u16,
+    pub backend: String,
+    #[serde(with = "hex::serde")]
+    pub measurement: [u8; 32],
+    #[serde(with = "hex::serde")]
+    pub tee_public_key: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    pub operator: [u8; 20],
+    pub timestamp: u64,
+    #[serde(with = "hex::serde")]
+    pub signer: Vec<u8>, // SEC1 compressed key that signed the report
+    #[serde(with = "hex::serde")]
+    pub signature: Vec<u8>, // r || s || v over report_digest()
+    pub chain: Vec<Endorsement>, // signer -> ... -> root
+}
+
+/// `issuer` vouches for the key one step closer to the report signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endorsement {
+    #[serde(with = "hex::serde")]
+    pub issuer: Vec<u8>,
+    #[serde(with = "hex::serde")]
+    pub signature: Vec<u8>,
+}
+
+impl AttestationDocument {
+    // Produce a signed document with the given attester
+    pub fn create(
+        attester: &dyn Attester,
+        measurement: [u8; 32],
+        tee_public_key: Vec<u8>,
+        operator: [u8; 20],
+        timestamp: u64,
+    ) -> Result<Self, String> {
+        let mut document = Self {
+            version: ATTESTATION_VERSION,
+            backend: attester.backend().to_string(),
+            measurement,
+            tee_public_key,
+            operator,
+            timestamp,
+            signer: attester.signer_public_key(),
+            signature: Vec::new(),
+            chain: attester.endorsements(),
+        };
+
+        document.signature = attester.sign_report(&document.report_digest())?;
+        Ok(document)
+    }
+
+    // keccak256(abi.encodePacked(domain, version, measurement, keccak256(key), operator, timestamp))
+    pub fn report_digest(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(REPORT_DOMAIN);
+        hasher.update(self.version.to_be_bytes());
+        hasher.update(self.measurement);
+        hasher.update(Keccak256::digest(&self.tee_public_key));
+        hasher.update(self.operator);
+        hasher.update(self.timestamp.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+impl Endorsement {
+    // Digest an issuer signs to endorse `subject`
+    pub fn digest(subject: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(ENDORSEMENT_DOMAIN);
+        hasher.update(subject);
+        hasher.finalize().into()
+    }
+}
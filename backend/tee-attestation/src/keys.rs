@@ -0,0 +1,39 @@
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+
+// SEC1 compressed encoding of a signing key's public half
+pub fn public_key_bytes(key: &SigningKey) -> Vec<u8> {
+    key.verifying_key()
+        .to_encoded_point(true)
+        .as_bytes()
+        .to_vec()
+}
+
+// Sign a prehashed digest, returning the 65-byte r || s || v form
+pub(crate) fn sign_digest(key: &SigningKey, digest: &[u8; 32]) -> Result<Vec<u8>, String> {
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(digest)
+        .map_err(|e| e.to_string())?;
+
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte());
+    Ok(bytes)
+}
+
+// Recover the SEC1 compressed public key that produced a 65-byte signature
+pub fn recover_signer(digest: &[u8; 32], signature: &[u8]) -> Result<Vec<u8>, String> {
+    if signature.len() != 65 {
+        return Err(format!("Invalid signature length: {}", signature.len()));
+    }
+
+    let parsed = Signature::from_slice(&signature[..64]).map_err(|e| e.to_string())?;
+    // Accept both the raw 0/1 and the Ethereum 27/28 recovery byte
+    let v = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_byte(v).ok_or("Invalid recovery id")?;
+
+    let key = VerifyingKey::recover_from_prehash(digest, &parsed, recovery_id)
+        .map_err(|e| e.to_string())?;
+    Ok(key.to_encoded_point(true).as_bytes().to_vec())
+}
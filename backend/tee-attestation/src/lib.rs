@@ -0,0 +1,50 @@
+//! Attestation documents for the drew-v TEE.
+//!
+//! A document binds three things together under a signature from a pluggable
+//! [`Attester`]: the measurement of the running binary, the public key users
+//! encrypt their commitment payloads to, and the operator address running it.
+
+#[cfg(feature = "client")]
+pub mod client;
+mod document;
+mod keys;
+mod software;
+pub mod verify;
+
+pub use document::{AttestationDocument, Endorsement, ATTESTATION_VERSION};
+pub use keys::{public_key_bytes, recover_signer};
+pub use software::SoftwareAttester;
+pub use verify::{verify_document, AttestationPolicy, RejectReason, Verdict};
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// A backend able to sign attestation reports.
+///
+/// Hardware backends (SEV-SNP, TDX, Nitro) sign with a key rooted in the
+/// vendor's certificate chain; [`SoftwareAttester`] simulates one for local
+/// testing.
+pub trait Attester: Send + Sync {
+    // Name of the backend, recorded in the document
+    fn backend(&self) -> &'static str;
+
+    // SEC1 compressed public key of the key that signs reports
+    fn signer_public_key(&self) -> Vec<u8>;
+
+    // Endorsements from the signer key up to a root key
+    fn endorsements(&self) -> Vec<Endorsement>;
+
+    // Sign a report digest, returning r || s || v
+    fn sign_report(&self, digest: &[u8; 32]) -> Result<Vec<u8>, String>;
+}
+
+// Measure a binary as the SHA-256 of its contents
+pub fn measure_file(path: &Path) -> std::io::Result<[u8; 32]> {
+    let bytes = std::fs::read(path)?;
+    Ok(Sha256::digest(&bytes).into())
+}
+
+// Measure the currently running executable
+pub fn measure_current_exe() -> std::io::Result<[u8; 32]> {
+    measure_file(&std::env::current_exe()?)
+}
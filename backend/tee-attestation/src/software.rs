@@ -0,0 +1,114 @@
+use k256::ecdsa::SigningKey;
+
+use crate::keys::{public_key_bytes, sign_digest};
+use crate::{Attester, Endorsement};
+
+/// Simulated attester for local testing.
+///
+/// A long-lived root key endorses a per-process signing key, mimicking the
+/// vendor root -> platform key -> report key chain of real TEEs. Documents
+/// it produces carry no hardware guarantee and verifiers should only trust
+/// its root in development.
+pub struct SoftwareAttester {
+    root: SigningKey,
+    signer: SigningKey,
+    endorsement: Endorsement,
+}
+
+impl SoftwareAttester {
+    pub fn new(root: SigningKey) -> Result<Self, String> {
+        let signer = SigningKey::random(&mut rand::thread_rng());
+        let signature = sign_digest(&root, &Endorsement::digest(&public_key_bytes(&signer)))?;
+        let endorsement = Endorsement {
+            issuer: public_key_bytes(&root),
+            signature,
+        };
+
+        Ok(Self {
+            root,
+            signer,
+            endorsement,
+        })
+    }
+
+    // Load the root from a 32-byte secret, e.g. ATTESTER_ROOT_KEY
+    pub fn from_root_bytes(bytes: &[u8; 32]) -> Result<Self, String> {
+        let root = SigningKey::from_slice(bytes).map_err(|e| e.to_string())?;
+        Self::new(root)
+    }
+
+    // Generate a throwaway root
+    pub fn random() -> Result<Self, String> {
+        Self::new(SigningKey::random(&mut rand::thread_rng()))
+    }
+
+    // Root key that verifiers must trust to accept this attester's documents
+    pub fn root_public_key(&self) -> Vec<u8> {
+        public_key_bytes(&self.root)
+    }
+}
+
+impl Attester for SoftwareAttester {
+    fn backend(&self) -> &'static str {
+        "software"
+    }
+
+    fn signer_public_key(&self) -> Vec<u8> {
+        public_key_bytes(&self.signer)
+    }
+
+    fn endorsements(&self) -> Vec<Endorsement> {
+        vec![self.endorsement.clone()]
+    }
+
+    fn sign_report(&self, digest: &[u8; 32]) -> Result<Vec<u8>, String> {
+        sign_digest(&self.signer, digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{recover_signer, AttestationDocument};
+
+    #[test]
+    fn test_document_signature_recovers_signer() {
+        let attester = SoftwareAttester::random().unwrap();
+        let document = AttestationDocument::create(
+            &attester,
+            [7u8; 32],
+            vec![2u8; 33],
+            [1u8; 20],
+            1_700_000_000,
+        )
+        .unwrap();
+
+        let signer = recover_signer(&document.report_digest(), &document.signature).unwrap();
+        assert_eq!(signer, document.signer);
+        assert_eq!(document.backend, "software");
+    }
+
+    #[test]
+    fn test_endorsement_chains_to_root() {
+        let attester = SoftwareAttester::random().unwrap();
+        let endorsement = &attester.endorsements()[0];
+
+        let issuer = recover_signer(
+            &Endorsement::digest(&attester.signer_public_key()),
+            &endorsement.signature,
+        )
+        .unwrap();
+        assert_eq!(issuer, attester.root_public_key());
+        assert_eq!(endorsement.issuer, attester.root_public_key());
+    }
+
+    #[test]
+    fn test_digest_changes_with_bound_key() {
+        let attester = SoftwareAttester::random().unwrap();
+        let a =
+            AttestationDocument::create(&attester, [0u8; 32], vec![1u8; 33], [0u8; 20], 0).unwrap();
+        let b =
+            AttestationDocument::create(&attester, [0u8; 32], vec![2u8; 33], [0u8; 20], 0).unwrap();
+        assert_ne!(a.report_digest(), b.report_digest());
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::{recover_signer, AttestationDocument, Endorsement, ATTESTATION_VERSION};
+
+// How far ahead of the client's clock a document may be dated
+pub const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// What a client is willing to accept from an AVS.
+#[derive(Debug, Clone, Default)]
+pub struct AttestationPolicy {
+    pub trusted_roots: Vec<Vec<u8>>,         // SEC1 compressed root keys
+    pub allowed_measurements: Vec<[u8; 32]>, // Reviewed drew-v builds
+    pub max_age_secs: Option<u64>,           // Reject documents older than this
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Trusted {
+        backend: String,
+        measurement: [u8; 32],
+        operator: [u8; 20],
+        tee_public_key: Vec<u8>,
+    },
+    Rejected(RejectReason),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    UnsupportedVersion(u16),
+    InvalidSignature(String),
+    BrokenChain { depth: usize },
+    UntrustedRoot(Vec<u8>),
+    MeasurementNotAllowed([u8; 32]),
+    KeyMismatch,
+    Stale { age_secs: u64 },
+    FromFuture { ahead_secs: u64 },
+}
+
+impl Verdict {
+    pub fn is_trusted(&self) -> bool {
+        matches!(self, Verdict::Trusted { .. })
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::UnsupportedVersion(v) => {
+                write!(f, "unsupported attestation version {}", v)
+            }
+            RejectReason::InvalidSignature(e) => write!(f, "invalid report signature: {}", e),
+            RejectReason::BrokenChain { depth } => {
+                write!(f, "endorsement {} does not match its issuer", depth)
+            }
+            RejectReason::UntrustedRoot(root) => {
+                write!(f, "untrusted root 0x{}", hex::encode(root))
+            }
+            RejectReason::MeasurementNotAllowed(m) => {
+                write!(f, "measurement 0x{} is not allowed", hex::encode(m))
+            }
+            RejectReason::KeyMismatch => {
+                write!(f, "attested key does not match FaceVerifier.teePublicKey")
+            }
+            RejectReason::Stale { age_secs } => write!(f, "attestation is {}s old", age_secs),
+            RejectReason::FromFuture { ahead_secs } => {
+                write!(f, "attestation is dated {}s in the future", ahead_secs)
+            }
+        }
+    }
+}
+
+// Check a document against a policy and the key published on-chain
+pub fn verify_document(
+    document: &AttestationDocument,
+    policy: &AttestationPolicy,
+    expected_tee_key: &[u8],
+    now: u64,
+) -> Verdict {
+    let reject = Verdict::Rejected;
+
+    if document.version != ATTESTATION_VERSION {
+        return reject(RejectReason::UnsupportedVersion(document.version));
+    }
+
+    // 1. The report is signed by the key the document names
+    match recover_signer(&document.report_digest(), &document.signature) {
+        Ok(signer) if signer == document.signer => {}
+        Ok(_) => {
+            return reject(RejectReason::InvalidSignature(
+                "signer mismatch".to_string(),
+            ))
+        }
+        Err(e) => return reject(RejectReason::InvalidSignature(e)),
+    }
+
+    // 2. Each endorsement vouches for the previous key, ending at a trusted root
+    let mut subject = document.signer.clone();
+    for (depth, endorsement) in document.chain.iter().enumerate() {
+        match recover_signer(&Endorsement::digest(&subject), &endorsement.signature) {
+            Ok(issuer) if issuer == endorsement.issuer => subject = issuer,
+            _ => return reject(RejectReason::BrokenChain { depth }),
+        }
+    }
+    if !policy.trusted_roots.contains(&subject) {
+        return reject(RejectReason::UntrustedRoot(subject));
+    }
+
+    // 3. The binary is one we reviewed
+    if !policy.allowed_measurements.contains(&document.measurement) {
+        return reject(RejectReason::MeasurementNotAllowed(document.measurement));
+    }
+
+    // 4. The attested key is the one the contract tells users to encrypt to
+    if document.tee_public_key != expected_tee_key {
+        return reject(RejectReason::KeyMismatch);
+    }
+
+    // A future date would otherwise count as age 0 and never go stale
+    let ahead_secs = document.timestamp.saturating_sub(now);
+    if ahead_secs > MAX_CLOCK_SKEW_SECS {
+        return reject(RejectReason::FromFuture { ahead_secs });
+    }
+
+    if let Some(max_age) = policy.max_age_secs {
+        let age_secs = now.saturating_sub(document.timestamp);
+        if age_secs > max_age {
+            return reject(RejectReason::Stale { age_secs });
+        }
+    }
+
+    Verdict::Trusted {
+        backend: document.backend.clone(),
+        measurement: document.measurement,
+        operator: document.operator,
+        tee_public_key: document.tee_public_key.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Attester, SoftwareAttester};
+
+    fn setup() -> (SoftwareAttester, AttestationDocument, AttestationPolicy) {
+        let attester = SoftwareAttester::random().unwrap();
+        let document =
+            AttestationDocument::create(&attester, [9u8; 32], vec![3u8; 33], [4u8; 20], 1_000)
+                .unwrap();
+        let policy = AttestationPolicy {
+            trusted_roots: vec![attester.root_public_key()],
+            allowed_measurements: vec![[9u8; 32]],
+            max_age_secs: Some(60),
+        };
+        (attester, document, policy)
+    }
+
+    #[test]
+    fn test_trusted_document() {
+        let (_, document, policy) = setup();
+        assert!(verify_document(&document, &policy, &[3u8; 33], 1_030).is_trusted());
+    }
+
+    #[test]
+    fn test_rejections() {
+        let (attester, document, policy) = setup();
+
+        assert_eq!(
+            verify_document(&document, &policy, &[5u8; 33], 1_030),
+            Verdict::Rejected(RejectReason::KeyMismatch)
+        );
+        assert_eq!(
+            verify_document(&document, &policy, &[3u8; 33], 2_000),
+            Verdict::Rejected(RejectReason::Stale { age_secs: 1_000 })
+        );
+
+        // Within the allowed skew a document dated ahead is fine; beyond it,
+        // it is rejected rather than treated as brand new
+        assert!(
+            verify_document(&document, &policy, &[3u8; 33], 1_000 - MAX_CLOCK_SKEW_SECS)
+                .is_trusted()
+        );
+        assert_eq!(
+            verify_document(
+                &document,
+                &policy,
+                &[3u8; 33],
+                1_000 - MAX_CLOCK_SKEW_SECS - 1
+            ),
+            Verdict::Rejected(RejectReason::FromFuture {
+                ahead_secs: MAX_CLOCK_SKEW_SECS + 1
+            })
+        );
+
+        let mut tampered = document.clone();
+        tampered.measurement = [8u8; 32];
+        assert!(matches!(
+            verify_document(&tampered, &policy, &[3u8; 33], 1_030),
+            Verdict::Rejected(RejectReason::InvalidSignature(_))
+        ));
+
+        let other = SoftwareAttester::random().unwrap();
+        let mut rerooted = document.clone();
+        rerooted.chain = other.endorsements();
+        assert_eq!(
+            verify_document(&rerooted, &policy, &[3u8; 33], 1_030),
+            Verdict::Rejected(RejectReason::BrokenChain { depth: 0 })
+        );
+
+        let strict = AttestationPolicy {
+            trusted_roots: vec![other.root_public_key()],
+            ..policy.clone()
+        };
+        assert_eq!(
+            verify_document(&document, &strict, &[3u8; 33], 1_030),
+            Verdict::Rejected(RejectReason::UntrustedRoot(attester.root_public_key()))
+        );
+    }
+}